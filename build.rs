@@ -0,0 +1,15 @@
+//! Generates the client code of protocols that wayland-protocols lacks.
+
+use std::env;
+use std::path::Path;
+use wayland_scanner::{generate_code, Side};
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let protocols = ["cursor-shape-v1", "fractional-scale-v1"];
+    for name in &protocols {
+        let xml = format!("protocols/{}.xml", name);
+        println!("cargo:rerun-if-changed={}", xml);
+        generate_code(&xml, Path::new(&out_dir).join(format!("{}_client_api.rs", name)), Side::Client);
+    }
+}
@@ -0,0 +1,33 @@
+//! The drawing of the house, shared with the golden image test.
+
+use pathfinder_canvas::{CanvasRenderingContext2D, Path2D};
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::Vector2F;
+
+pub fn door() -> RectF {
+    RectF::new(Vector2F::new(130.0, 190.0), Vector2F::new(40.0, 60.0))
+}
+
+pub fn draw_house(canvas: &mut CanvasRenderingContext2D) {
+    // We're going to draw a house.
+
+    // Set line width.
+    canvas.set_line_width(10.0);
+
+    // Draw walls.
+    canvas.stroke_rect(RectF::new(
+        Vector2F::new(75.0, 140.0),
+        Vector2F::new(150.0, 110.0),
+    ));
+
+    // Draw door.
+    canvas.fill_rect(door());
+
+    // Draw roof.
+    let mut path = Path2D::new();
+    path.move_to(Vector2F::new(50.0, 140.0));
+    path.line_to(Vector2F::new(150.0, 60.0));
+    path.line_to(Vector2F::new(250.0, 140.0));
+    path.close_path();
+    canvas.stroke_path(path);
+}
@@ -0,0 +1,49 @@
+mod drawing;
+
+use bean::{Application, CursorShape, Headless, InputEvent, Window, WindowHandle};
+use drawing::{door, draw_house};
+use pathfinder_canvas::CanvasRenderingContext2D;
+use pathfinder_geometry::vector::Vector2I;
+
+struct House;
+
+impl Application for House {
+    fn draw(&mut self, canvas: &mut CanvasRenderingContext2D) {
+        draw_house(canvas);
+    }
+
+    fn on_input(&mut self, window: &WindowHandle, _seat: &str, event: InputEvent) {
+        // Show a hand while the pointer is over the door.
+        let position = match event {
+            InputEvent::PointerEnter { position, .. } | InputEvent::PointerMotion { position, .. } => position,
+            _ => return,
+        };
+        if door().contains_point(position) {
+            window.set_cursor(CursorShape::Pointer);
+        } else {
+            window.set_cursor(CursorShape::Default);
+        }
+    }
+}
+
+fn main() {
+    // `house --png <path>` renders the house to a file without a compositor.
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() == 3 && args[1] == "--png" {
+        let result = Headless::new(Vector2I::new(320, 240)).and_then(|mut headless| headless.render(draw_house));
+        match result {
+            Ok(image) => image.save_png(&args[2]).unwrap_or_else(|err| exit(err)),
+            Err(err) => exit(err),
+        }
+        return;
+    }
+
+    if let Err(err) = Window::new("house").and_then(|window| window.run(House)) {
+        exit(err);
+    }
+}
+
+fn exit(err: impl std::fmt::Display) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
@@ -0,0 +1,321 @@
+//! EGL setup for drawing to a Wayland surface with OpenGL.
+
+use crate::error::Error;
+use crate::render;
+use khronos_egl::{self as egl, Context as EGLContext, Display as EGLDisplay};
+use pathfinder_color::ColorF;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gl::GLDevice;
+use pathfinder_renderer::gpu::renderer::Renderer;
+use std::fmt;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::Display;
+use wayland_egl::WlEglSurface;
+
+/// The OpenGL flavors bean can render with, in order of preference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlVersion {
+    /// OpenGL 3.3 with the core profile.
+    Gl33Core,
+    /// OpenGL 3.2 with the core profile.
+    Gl32Core,
+    /// OpenGL ES 3.0.
+    Gles30,
+}
+
+impl GlVersion {
+    const CANDIDATES: [GlVersion; 3] = [GlVersion::Gl33Core, GlVersion::Gl32Core, GlVersion::Gles30];
+
+    /// Returns the candidates to try after `self` did not work out.
+    fn fallbacks(self, candidates: &[GlVersion]) -> &[GlVersion] {
+        match candidates.iter().position(|&candidate| candidate == self) {
+            Some(index) => &candidates[index + 1..],
+            None => candidates,
+        }
+    }
+
+    /// Returns the matching shader dialect of Pathfinder.
+    pub(crate) fn pathfinder(self) -> pathfinder_gl::GLVersion {
+        match self {
+            GlVersion::Gl33Core | GlVersion::Gl32Core => pathfinder_gl::GLVersion::GL3,
+            GlVersion::Gles30 => pathfinder_gl::GLVersion::GLES3,
+        }
+    }
+
+    fn api(self) -> egl::Enum {
+        match self {
+            GlVersion::Gl33Core | GlVersion::Gl32Core => egl::OPENGL_API,
+            GlVersion::Gles30 => egl::OPENGL_ES_API,
+        }
+    }
+
+    fn renderable_type(self) -> egl::Int {
+        match self {
+            GlVersion::Gl33Core | GlVersion::Gl32Core => egl::OPENGL_BIT,
+            GlVersion::Gles30 => egl::OPENGL_ES3_BIT,
+        }
+    }
+
+    fn context_attributes(self) -> &'static [egl::Int] {
+        match self {
+            GlVersion::Gl33Core => &[
+                egl::CONTEXT_MAJOR_VERSION, 3,
+                egl::CONTEXT_MINOR_VERSION, 3,
+                egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                egl::NONE,
+            ],
+            GlVersion::Gl32Core => &[
+                egl::CONTEXT_MAJOR_VERSION, 3,
+                egl::CONTEXT_MINOR_VERSION, 2,
+                egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                egl::NONE,
+            ],
+            GlVersion::Gles30 => &[
+                egl::CONTEXT_MAJOR_VERSION, 3,
+                egl::CONTEXT_MINOR_VERSION, 0,
+                egl::NONE,
+            ],
+        }
+    }
+}
+
+impl fmt::Display for GlVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GlVersion::Gl33Core => f.write_str("OpenGL 3.3 core"),
+            GlVersion::Gl32Core => f.write_str("OpenGL 3.2 core"),
+            GlVersion::Gles30 => f.write_str("OpenGL ES 3.0"),
+        }
+    }
+}
+
+/// An OpenGL context rendering to a Wayland surface.
+///
+/// Dropping it destroys the context and the EGL display connection, all
+/// OpenGL resources must be released before.
+pub(crate) struct Context {
+    display: EGLDisplay,
+    context: EGLContext,
+    gl_version: GlVersion,
+    // Set if the buffers have an alpha channel.
+    transparent: bool,
+    surface: egl::Surface,
+    // Dropped after the EGL surface using it was destroyed.
+    wl_egl_surface: WlEglSurface,
+}
+
+impl Context {
+    /// Creates a context for `surface` and makes it current, together with a
+    /// renderer clearing to `background`.
+    pub(crate) fn new(
+        display: &Display,
+        surface: &WlSurface,
+        size: Vector2I,
+        background: ColorF,
+    ) -> Result<(Context, Renderer<GLDevice>), Error> {
+        if !wayland_egl::is_available() {
+            return Err(Error::WaylandEglUnavailable);
+        }
+
+        // Initialize OpenGL
+        let native_display = unsafe { egl::NativeDisplayType::from_ptr(display.get_display_ptr() as *mut std::ffi::c_void) };
+        let egl_display = egl::get_display(native_display).ok_or(Error::EglInit(None))?;
+        egl::initialize(egl_display).map_err(|err| Error::EglInit(Some(err)))?;
+        let wl_egl_surface = WlEglSurface::new(surface, size.x(), size.y());
+        let created = create_with_renderer(egl_display, egl::WINDOW_BIT, size, background, |config| {
+            unsafe {
+                egl::create_window_surface(
+                    egl_display,
+                    config,
+                    egl::NativeWindowType::from_ptr(wl_egl_surface.ptr() as *mut std::ffi::c_void),
+                    None,
+                )
+            }
+            .map_err(Error::SurfaceCreation)
+        });
+        let (egl_context, egl_config, egl_surface, gl_version, renderer) = match created {
+            Ok(created) => created,
+            Err(err) => {
+                let _ = egl::terminate(egl_display);
+                return Err(err);
+            }
+        };
+        // Frames are throttled with our own frame callbacks, eglSwapBuffers must
+        // not block waiting for the compositor.
+        //
+        // Failing to do so is not fatal, it only costs some latency.
+        let _ = egl::swap_interval(egl_display, 0);
+
+        let alpha_size = egl::get_config_attrib(egl_display, egl_config, egl::ALPHA_SIZE).unwrap_or(0);
+        let context = Context {
+            display: egl_display,
+            context: egl_context,
+            gl_version,
+            transparent: alpha_size > 0,
+            surface: egl_surface,
+            wl_egl_surface,
+        };
+        Ok((context, renderer))
+    }
+
+    /// Returns the OpenGL flavor of the context.
+    pub(crate) fn gl_version(&self) -> GlVersion {
+        self.gl_version
+    }
+
+    /// Returns true if transparent pixels let the desktop shine through.
+    ///
+    /// Without an alpha channel they are shown black.
+    pub(crate) fn transparent(&self) -> bool {
+        self.transparent
+    }
+
+    /// Makes the context current on this thread.
+    ///
+    /// Other windows and headless renderers on the thread use their own
+    /// contexts, so this is needed before every use of the renderer.
+    pub(crate) fn make_current(&self) -> Result<(), Error> {
+        egl::make_current(self.display, Some(self.surface), Some(self.surface), Some(self.context))
+            .map_err(Error::SurfaceCreation)
+    }
+
+    /// Changes the size of the buffers that are drawn to.
+    ///
+    /// Takes effect with the next frame.
+    pub(crate) fn resize(&self, size: Vector2I) {
+        self.wl_egl_surface.resize(size.x(), size.y(), 0, 0);
+    }
+
+    /// Presents the rendered frame.
+    ///
+    /// Swapping the buffers attaches the rendered image to the surface and
+    /// commits it.
+    pub(crate) fn swap_buffers(&self) -> Result<(), Error> {
+        egl::swap_buffers(self.display, self.surface).map_err(Error::SwapBuffers)
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        // Nothing can be done about errors this late, the display connection
+        // is gone anyway once terminated.
+        let _ = egl::make_current(self.display, None, None, None);
+        let _ = egl::destroy_surface(self.display, self.surface);
+        let _ = egl::destroy_context(self.display, self.context);
+        let _ = egl::terminate(self.display);
+    }
+}
+
+/// Loads the OpenGL functions through EGL.
+fn load_gl() {
+    // Functions the driver does not provide are left null.
+    gl::load_with(|name| {
+        egl::get_proc_address(name).map_or(std::ptr::null(), |f| f as *const std::ffi::c_void)
+    });
+}
+
+/// Creates a context, a surface and a renderer with the first OpenGL flavor
+/// that works.
+///
+/// `create_surface` creates a surface of `surface_type` for a config, the
+/// context is made current with it. Drivers may create a context whose
+/// surfaces or shaders then fail, the next flavor is tried in that case.
+/// Everything created for a failed flavor is destroyed again, the display is
+/// left to the caller.
+pub(crate) fn create_with_renderer<F>(
+    display: EGLDisplay,
+    surface_type: egl::Int,
+    size: Vector2I,
+    background: ColorF,
+    mut create_surface: F,
+) -> Result<(EGLContext, egl::Config, egl::Surface, GlVersion, Renderer<GLDevice>), Error>
+where
+    F: FnMut(egl::Config) -> Result<egl::Surface, Error>,
+{
+    let mut candidates = &GlVersion::CANDIDATES[..];
+    loop {
+        let (context, config, gl_version) = create_context(display, surface_type, candidates)?;
+        let created = create_surface(config).and_then(|surface| {
+            let renderer = egl::make_current(display, Some(surface), Some(surface), Some(context))
+                .map_err(Error::SurfaceCreation)
+                .and_then(|()| {
+                    load_gl();
+                    render::create_renderer(size, gl_version, background)
+                });
+            match renderer {
+                Ok(renderer) => Ok((surface, renderer)),
+                Err(err) => {
+                    let _ = egl::make_current(display, None, None, None);
+                    let _ = egl::destroy_surface(display, surface);
+                    Err(err)
+                }
+            }
+        });
+        match created {
+            Ok((surface, renderer)) => return Ok((context, config, surface, gl_version, renderer)),
+            Err(err) => {
+                let _ = egl::destroy_context(display, context);
+                candidates = gl_version.fallbacks(candidates);
+                if candidates.is_empty() {
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+/// Creates an OpenGL context for surfaces of the given type.
+///
+/// `surface_type` is a mask of `egl::WINDOW_BIT`, `egl::PBUFFER_BIT`, ...
+///
+/// Tries `candidates` in order and returns the first one the display
+/// supports. The matching API is left bound for the current thread.
+fn create_context(
+    display: EGLDisplay,
+    surface_type: egl::Int,
+    candidates: &[GlVersion],
+) -> Result<(EGLContext, egl::Config, GlVersion), Error> {
+    let mut error = Error::NoEglConfig;
+    for &gl_version in candidates {
+        if egl::bind_api(gl_version.api()).is_err() {
+            continue;
+        }
+
+        // An alpha channel is needed for transparent parts like the window
+        // shadow, but the window works fine without.
+        let mut config = None;
+        for &alpha_size in &[8, 0] {
+            let attributes = [
+                egl::SURFACE_TYPE, surface_type,
+                egl::RENDERABLE_TYPE, gl_version.renderable_type(),
+                egl::RED_SIZE, 8,
+                egl::GREEN_SIZE, 8,
+                egl::BLUE_SIZE, 8,
+                egl::ALPHA_SIZE, alpha_size,
+                egl::NONE,
+            ];
+            match egl::choose_first_config(display, &attributes) {
+                Ok(Some(found)) => {
+                    config = Some(found);
+                    break;
+                }
+                Ok(None) => {}
+                // Another API may still work.
+                Err(err) => {
+                    error = Error::EglInit(Some(err));
+                    break;
+                }
+            }
+        }
+        let config = match config {
+            Some(config) => config,
+            None => continue,
+        };
+
+        match egl::create_context(display, config, None, gl_version.context_attributes()) {
+            Ok(context) => return Ok((context, config, gl_version)),
+            Err(err) => error = Error::ContextCreation(err),
+        }
+    }
+    Err(error)
+}
@@ -0,0 +1,191 @@
+//! Pointer cursors.
+//!
+//! If the compositor supports `wp_cursor_shape_v1` it draws the cursor from
+//! the shape, so it matches other applications. Otherwise cursors are loaded
+//! from the XCursor theme of the user.
+
+use crate::protocols::cursor_shape_v1::client::wp_cursor_shape_device_v1::Shape;
+use std::env;
+use wayland_client::protocol::{wl_pointer, wl_shm, wl_surface};
+use wayland_client::Attached;
+use wayland_cursor::CursorTheme;
+
+/// Theme used if `XCURSOR_THEME` is not set.
+const DEFAULT_THEME: &str = "default";
+/// Cursor size in pixels used if `XCURSOR_SIZE` is not set.
+const DEFAULT_SIZE: u32 = 24;
+
+/// The look of the pointer cursor.
+///
+/// Names follow the CSS `cursor` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CursorShape {
+    /// The normal arrow.
+    Default,
+    /// Text that can be selected.
+    Text,
+    /// A link or button, usually a hand.
+    Pointer,
+    /// Precise selection.
+    Crosshair,
+    /// Something that can be moved.
+    Move,
+    /// The action is not allowed.
+    NotAllowed,
+    /// The application is busy.
+    Wait,
+    /// Resizing the top edge.
+    NResize,
+    /// Resizing the bottom edge.
+    SResize,
+    /// Resizing the right edge.
+    EResize,
+    /// Resizing the left edge.
+    WResize,
+    /// Resizing the top right corner.
+    NeResize,
+    /// Resizing the top left corner.
+    NwResize,
+    /// Resizing the bottom right corner.
+    SeResize,
+    /// Resizing the bottom left corner.
+    SwResize,
+    /// Resizing horizontally.
+    EwResize,
+    /// Resizing vertically.
+    NsResize,
+}
+
+impl Default for CursorShape {
+    fn default() -> CursorShape {
+        CursorShape::Default
+    }
+}
+
+impl CursorShape {
+    /// Returns the matching shape of `wp_cursor_shape_v1`.
+    pub(crate) fn wp_shape(self) -> Shape {
+        match self {
+            CursorShape::Default => Shape::Default,
+            CursorShape::Text => Shape::Text,
+            CursorShape::Pointer => Shape::Pointer,
+            CursorShape::Crosshair => Shape::Crosshair,
+            CursorShape::Move => Shape::Move,
+            CursorShape::NotAllowed => Shape::NotAllowed,
+            CursorShape::Wait => Shape::Wait,
+            CursorShape::NResize => Shape::NResize,
+            CursorShape::SResize => Shape::SResize,
+            CursorShape::EResize => Shape::EResize,
+            CursorShape::WResize => Shape::WResize,
+            CursorShape::NeResize => Shape::NeResize,
+            CursorShape::NwResize => Shape::NwResize,
+            CursorShape::SeResize => Shape::SeResize,
+            CursorShape::SwResize => Shape::SwResize,
+            CursorShape::EwResize => Shape::EwResize,
+            CursorShape::NsResize => Shape::NsResize,
+        }
+    }
+
+    /// Returns the XCursor names of the shape, the preferred name first.
+    ///
+    /// Older themes only provide the X11 core cursor names.
+    fn names(self) -> &'static [&'static str] {
+        match self {
+            CursorShape::Default => &["default", "left_ptr"],
+            CursorShape::Text => &["text", "xterm"],
+            CursorShape::Pointer => &["pointer", "hand2", "hand1"],
+            CursorShape::Crosshair => &["crosshair", "cross"],
+            CursorShape::Move => &["move", "fleur"],
+            CursorShape::NotAllowed => &["not-allowed", "crossed_circle"],
+            CursorShape::Wait => &["wait", "watch"],
+            CursorShape::NResize => &["n-resize", "top_side"],
+            CursorShape::SResize => &["s-resize", "bottom_side"],
+            CursorShape::EResize => &["e-resize", "right_side"],
+            CursorShape::WResize => &["w-resize", "left_side"],
+            CursorShape::NeResize => &["ne-resize", "top_right_corner"],
+            CursorShape::NwResize => &["nw-resize", "top_left_corner"],
+            CursorShape::SeResize => &["se-resize", "bottom_right_corner"],
+            CursorShape::SwResize => &["sw-resize", "bottom_left_corner"],
+            CursorShape::EwResize => &["ew-resize", "sb_h_double_arrow"],
+            CursorShape::NsResize => &["ns-resize", "sb_v_double_arrow"],
+        }
+    }
+}
+
+/// The cursor theme of the user.
+pub(crate) struct Cursors {
+    shm: Attached<wl_shm::WlShm>,
+    name: String,
+    // The size in logical pixels.
+    size: u32,
+    // The theme is loaded at `size * scale` pixels.
+    scale: i32,
+    theme: CursorTheme,
+}
+
+impl Cursors {
+    /// Loads the theme named by `XCURSOR_THEME` at the size `XCURSOR_SIZE`.
+    pub(crate) fn load(shm: &Attached<wl_shm::WlShm>) -> Cursors {
+        let name = env::var("XCURSOR_THEME").unwrap_or_else(|_| DEFAULT_THEME.to_owned());
+        let size = env::var("XCURSOR_SIZE")
+            .ok()
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(DEFAULT_SIZE);
+        Cursors {
+            shm: shm.clone(),
+            theme: CursorTheme::load_from_name(&name, size, shm),
+            name,
+            size,
+            scale: 1,
+        }
+    }
+
+    /// Loads the theme again for outputs with the given scale.
+    ///
+    /// Returns false if the scale did not change.
+    pub(crate) fn set_scale(&mut self, scale: i32) -> bool {
+        if scale == self.scale {
+            return false;
+        }
+        self.scale = scale;
+        self.theme = CursorTheme::load_from_name(&self.name, self.size * scale as u32, &self.shm);
+        true
+    }
+
+    /// Shows `shape` as the cursor of `pointer`.
+    ///
+    /// `surface` is the cursor surface of the pointer and `serial` the serial
+    /// of the last enter event. Returns false if the theme lacks the shape.
+    pub(crate) fn apply(
+        &mut self,
+        pointer: &wl_pointer::WlPointer,
+        surface: &wl_surface::WlSurface,
+        serial: u32,
+        shape: CursorShape,
+    ) -> bool {
+        let theme = &mut self.theme;
+        let name = match shape.names().iter().find(|name| theme.get_cursor(name).is_some()) {
+            Some(name) => name,
+            None => return false,
+        };
+        let cursor = match theme.get_cursor(name) {
+            Some(cursor) => cursor,
+            None => return false,
+        };
+        // Animated cursors are shown with their first frame.
+        let image = &cursor[0];
+        let (width, height) = image.dimensions();
+        let (hotspot_x, hotspot_y) = image.hotspot();
+        // The image is scaled down again by the compositor, so the cursor
+        // keeps its size but stays sharp.
+        let scale = self.scale;
+        if surface.as_ref().version() >= 3 {
+            surface.set_buffer_scale(scale);
+        }
+        surface.attach(Some(&**image), 0, 0);
+        surface.damage(0, 0, width as i32 / scale, height as i32 / scale);
+        surface.commit();
+        pointer.set_cursor(serial, Some(surface), hotspot_x as i32 / scale, hotspot_y as i32 / scale);
+        true
+    }
+}
@@ -0,0 +1,565 @@
+//! Window decorations.
+//!
+//! Compositors implementing `zxdg_decoration_manager_v1` are asked to draw
+//! the title bar themselves, the whole surface is then left to the
+//! application. Otherwise bean draws a title bar with buttons and a shadow
+//! around the window, and lets the user move and resize the window with them.
+
+use crate::cursor::CursorShape;
+use crate::event::{InputEvent, MouseButton};
+use crate::shell::{ResizeEdge, ShellSurface, WindowState};
+use crate::window::WindowHandle;
+use pathfinder_canvas::{CanvasRenderingContext2D, Path2D, TextAlign, TextBaseline};
+use pathfinder_color::ColorU;
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wayland_client::{GlobalManager, Main};
+use wayland_protocols::unstable::xdg_decoration::v1::client::{
+    zxdg_decoration_manager_v1, zxdg_toplevel_decoration_v1,
+};
+
+/// Width of the shadow around the window, it also catches resize clicks.
+const SHADOW_WIDTH: i32 = 16;
+/// How far resize handles reach into the window.
+const RESIZE_INSET: f32 = 4.0;
+/// How far the corners reach along the edges of the window.
+const CORNER_SIZE: f32 = 20.0;
+const TITLE_HEIGHT: i32 = 32;
+const TITLE_FONT_SIZE: f32 = 14.0;
+const BUTTON_WIDTH: f32 = 40.0;
+/// Size of the symbols on the buttons.
+const ICON_SIZE: f32 = 10.0;
+
+/// The buttons in the title bar from right to left.
+const BUTTONS: [Button; 3] = [Button::Close, Button::Maximize, Button::Minimize];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Button {
+    Close,
+    Maximize,
+    Minimize,
+}
+
+/// The parts of the window the pointer can be over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Part {
+    Content,
+    Title,
+    Button(Button),
+    Edge(ResizeEdge),
+}
+
+/// Something the user requested by clicking on the decorations.
+pub(crate) enum Action {
+    Move { seat: String, serial: u32 },
+    Resize { seat: String, serial: u32, edge: ResizeEdge },
+    Close,
+    ToggleMaximized,
+    Minimize,
+}
+
+/// The title bar and shadow of the window.
+pub(crate) struct Decorations {
+    decoration: Option<Main<zxdg_toplevel_decoration_v1::ZxdgToplevelDecorationV1>>,
+    // Set while the compositor draws the decorations.
+    server_side: Rc<Cell<bool>>,
+    // Cleared if the application does not want bean's decorations.
+    enabled: bool,
+    // Cleared if the surface cannot show the shadow.
+    transparent: bool,
+    window_state: Rc<Cell<WindowState>>,
+    title: String,
+    // The part of the window each pointer is over, by seat name.
+    pointers: HashMap<String, Part>,
+    // The button that was pressed and not yet released.
+    pressed: Option<Button>,
+    // The part and last position of every touch point, by seat name and id.
+    touches: HashMap<(String, i32), (Part, Vector2F)>,
+    // The button pressed by the primary touch point, by seat name and id.
+    touch_pressed: Option<(String, i32, Button)>,
+    // The serial of the last touch on the decorations. The emulated pointer
+    // press with the same serial was handled already.
+    touch_serial: Option<u32>,
+    actions: Vec<Action>,
+}
+
+impl Decorations {
+    /// Asks the compositor to decorate the window, if it knows how to.
+    ///
+    /// Must be called before the first commit of the surface.
+    pub(crate) fn new(
+        globals: &GlobalManager,
+        shell_surface: &ShellSurface,
+        title: &str,
+        window_state: Rc<Cell<WindowState>>,
+    ) -> Decorations {
+        let server_side = Rc::new(Cell::new(false));
+        let manager = globals.instantiate_exact::<zxdg_decoration_manager_v1::ZxdgDecorationManagerV1>(1);
+        let decoration = match (manager, shell_surface.toplevel()) {
+            (Ok(manager), Some(toplevel)) => {
+                let decoration = manager.get_toplevel_decoration(toplevel);
+                let server_side = server_side.clone();
+                // The compositor has the final say, it may still leave the
+                // decorations to us.
+                decoration.quick_assign(move |_, event, _| {
+                    if let zxdg_toplevel_decoration_v1::Event::Configure { mode } = event {
+                        server_side.set(mode == zxdg_toplevel_decoration_v1::Mode::ServerSide);
+                    }
+                });
+                decoration.set_mode(zxdg_toplevel_decoration_v1::Mode::ServerSide);
+                Some(decoration)
+            }
+            _ => None,
+        };
+        Decorations {
+            decoration,
+            server_side,
+            enabled: true,
+            transparent: true,
+            window_state,
+            title: title.to_owned(),
+            pointers: HashMap::new(),
+            pressed: None,
+            touches: HashMap::new(),
+            touch_pressed: None,
+            touch_serial: None,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Destroys the decoration object, must be called before the toplevel
+    /// is destroyed.
+    pub(crate) fn destroy(&self) {
+        if let Some(decoration) = &self.decoration {
+            decoration.destroy();
+        }
+    }
+
+    /// Returns true if the decorations are drawn by bean.
+    ///
+    /// Fullscreen windows have no decorations at all.
+    fn client_side(&self) -> bool {
+        self.enabled && !self.server_side.get() && !self.window_state.get().fullscreen
+    }
+
+    /// Turns bean's decorations on or off.
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        if enabled == self.enabled {
+            return;
+        }
+        self.enabled = enabled;
+        // The parts under pointers and fingers moved.
+        self.pointers.clear();
+        self.touches.clear();
+        self.pressed = None;
+        self.touch_pressed = None;
+    }
+
+    /// Tells whether the surface has an alpha channel.
+    ///
+    /// The shadow is left out without, it would be drawn as a black frame.
+    pub(crate) fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent;
+    }
+
+    /// Returns the width of the shadow on each side of the window.
+    fn margin(&self) -> i32 {
+        let state = self.window_state.get();
+        // Windows lined up with others or the screen edges have no shadow.
+        let tiled = state.tiled_left || state.tiled_right || state.tiled_top || state.tiled_bottom;
+        if self.client_side() && self.transparent && !state.maximized && !tiled {
+            SHADOW_WIDTH
+        } else {
+            0
+        }
+    }
+
+    /// Changes the title shown in the title bar.
+    pub(crate) fn set_title(&mut self, title: &str) {
+        self.title = title.to_owned();
+    }
+
+    /// Returns the window size needed for contents of `content_size`.
+    pub(crate) fn window_size(&self, content_size: Vector2I) -> Vector2I {
+        if self.client_side() {
+            Vector2I::new(content_size.x(), content_size.y() + TITLE_HEIGHT)
+        } else {
+            content_size
+        }
+    }
+
+    /// Returns the size of the surface for a window of `window_size`.
+    pub(crate) fn surface_size(&self, window_size: Vector2I) -> Vector2I {
+        let margin = self.margin();
+        Vector2I::new(window_size.x() + 2 * margin, window_size.y() + 2 * margin)
+    }
+
+    /// Returns the part of the surface that is the window, without the shadow.
+    pub(crate) fn window_geometry(&self, window_size: Vector2I) -> RectI {
+        let margin = self.margin();
+        RectI::new(Vector2I::new(margin, margin), window_size)
+    }
+
+    /// Returns the part of the surface the application draws to.
+    pub(crate) fn content_rect(&self, window_size: Vector2I) -> RectF {
+        let window = self.window_geometry(window_size).to_f32();
+        if !self.client_side() {
+            return window;
+        }
+        let title_height = TITLE_HEIGHT as f32;
+        RectF::new(
+            window.origin() + Vector2F::new(0.0, title_height),
+            Vector2F::new(window.width(), window.height() - title_height),
+        )
+    }
+
+    /// Returns the cursor to show, or `None` if the application decides.
+    pub(crate) fn cursor(&self) -> Option<CursorShape> {
+        let part = self.pointers.values().find(|part| **part != Part::Content)?;
+        Some(match part {
+            Part::Edge(ResizeEdge::Top) => CursorShape::NResize,
+            Part::Edge(ResizeEdge::Bottom) => CursorShape::SResize,
+            Part::Edge(ResizeEdge::Left) => CursorShape::WResize,
+            Part::Edge(ResizeEdge::Right) => CursorShape::EResize,
+            Part::Edge(ResizeEdge::TopLeft) => CursorShape::NwResize,
+            Part::Edge(ResizeEdge::TopRight) => CursorShape::NeResize,
+            Part::Edge(ResizeEdge::BottomLeft) => CursorShape::SwResize,
+            Part::Edge(ResizeEdge::BottomRight) => CursorShape::SeResize,
+            _ => CursorShape::Default,
+        })
+    }
+
+    /// Handles input on the decorations.
+    ///
+    /// Events for the content are returned with positions relative to the
+    /// content. Pointers moving between the decorations and the content
+    /// appear to enter and leave the window. Touch points that start on the
+    /// decorations are not reported, the first one of a seat acts like the
+    /// left pointer button.
+    pub(crate) fn handle_input(
+        &mut self,
+        handle: &WindowHandle,
+        seat: &str,
+        event: InputEvent,
+        window_size: Vector2I,
+    ) -> Option<InputEvent> {
+        let origin = self.content_rect(window_size).origin();
+        match event {
+            InputEvent::PointerEnter { position, serial } => {
+                let part = self.hit_test(position, window_size);
+                self.set_pointer(handle, seat, Some(part));
+                if part == Part::Content {
+                    Some(InputEvent::PointerEnter { position: position - origin, serial })
+                } else {
+                    None
+                }
+            }
+            InputEvent::PointerMotion { position, time } => {
+                let part = self.hit_test(position, window_size);
+                let previous = self.set_pointer(handle, seat, Some(part));
+                match (previous == Some(Part::Content), part == Part::Content) {
+                    (true, true) => Some(InputEvent::PointerMotion { position: position - origin, time }),
+                    (false, true) => Some(InputEvent::PointerEnter { position: position - origin, serial: None }),
+                    (true, false) => Some(InputEvent::PointerLeave { serial: None }),
+                    (false, false) => None,
+                }
+            }
+            InputEvent::PointerLeave { .. } => match self.set_pointer(handle, seat, None) {
+                Some(Part::Content) => Some(event),
+                _ => None,
+            },
+            InputEvent::PointerButton { pressed: true, serial, .. } if self.touch_serial == Some(serial) => {
+                self.touch_serial = None;
+                None
+            }
+            InputEvent::PointerButton { button, pressed, serial, .. } => {
+                let part = self.pointers.get(seat).copied().unwrap_or(Part::Content);
+                let released = if pressed { None } else { self.pressed.take() };
+                match part {
+                    Part::Content => return Some(event),
+                    Part::Title if pressed && button == MouseButton::Left => {
+                        self.actions.push(Action::Move {
+                            seat: seat.to_owned(),
+                            serial,
+                        });
+                    }
+                    Part::Edge(edge) if pressed && button == MouseButton::Left => {
+                        self.actions.push(Action::Resize {
+                            seat: seat.to_owned(),
+                            serial,
+                            edge,
+                        });
+                    }
+                    Part::Button(clicked) if button == MouseButton::Left => {
+                        if pressed {
+                            self.pressed = Some(clicked);
+                        } else if released == Some(clicked) {
+                            // A click only counts if the pointer was not moved
+                            // off the button in between.
+                            self.click(clicked);
+                        }
+                        handle.request_redraw();
+                    }
+                    _ => {}
+                }
+                None
+            }
+            InputEvent::PointerScroll(_) => match self.pointers.get(seat) {
+                Some(Part::Content) | None => Some(event),
+                _ => None,
+            },
+            InputEvent::TouchDown { mut point, time, serial } => {
+                let primary = !self.touches.keys().any(|(touch_seat, _)| touch_seat == seat);
+                let part = self.hit_test(point.position, window_size);
+                self.touches.insert((seat.to_owned(), point.id), (part, point.position));
+                if part == Part::Content {
+                    point.position = point.position - origin;
+                    return Some(InputEvent::TouchDown { point, time, serial });
+                }
+                if !primary {
+                    return None;
+                }
+                self.touch_serial = Some(serial);
+                match part {
+                    Part::Title => self.actions.push(Action::Move {
+                        seat: seat.to_owned(),
+                        serial,
+                    }),
+                    Part::Edge(edge) => self.actions.push(Action::Resize {
+                        seat: seat.to_owned(),
+                        serial,
+                        edge,
+                    }),
+                    Part::Button(button) => self.touch_pressed = Some((seat.to_owned(), point.id, button)),
+                    Part::Content => {}
+                }
+                None
+            }
+            InputEvent::TouchMotion { mut point, time } => {
+                if let Some((part, position)) = self.touches.get_mut(&(seat.to_owned(), point.id)) {
+                    *position = point.position;
+                    if *part != Part::Content {
+                        return None;
+                    }
+                }
+                point.position = point.position - origin;
+                Some(InputEvent::TouchMotion { point, time })
+            }
+            InputEvent::TouchUp { id, .. } => {
+                let (part, position) = match self.touches.remove(&(seat.to_owned(), id)) {
+                    Some(touch) => touch,
+                    None => return Some(event),
+                };
+                match self.touch_pressed.take() {
+                    // Like clicks, taps only count if the finger stayed on
+                    // the button.
+                    Some((touch_seat, touch_id, button)) if touch_seat == seat && touch_id == id => {
+                        if self.hit_test(position, window_size) == Part::Button(button) {
+                            self.click(button);
+                        }
+                    }
+                    pressed => self.touch_pressed = pressed,
+                }
+                if part == Part::Content {
+                    Some(event)
+                } else {
+                    None
+                }
+            }
+            InputEvent::TouchCancel => {
+                self.touches.retain(|(touch_seat, _), _| touch_seat != seat);
+                if self.touch_pressed.as_ref().map_or(false, |(touch_seat, _, _)| touch_seat == seat) {
+                    self.touch_pressed = None;
+                }
+                Some(event)
+            }
+            event => Some(event),
+        }
+    }
+
+    /// Performs the action of a clicked or tapped button.
+    fn click(&mut self, button: Button) {
+        self.actions.push(match button {
+            Button::Close => Action::Close,
+            Button::Maximize => Action::ToggleMaximized,
+            Button::Minimize => Action::Minimize,
+        });
+    }
+
+    /// Returns the actions requested since the last call.
+    pub(crate) fn take_actions(&mut self) -> Vec<Action> {
+        std::mem::replace(&mut self.actions, Vec::new())
+    }
+
+    /// Remembers the part `seat`'s pointer is over and returns the previous one.
+    fn set_pointer(&mut self, handle: &WindowHandle, seat: &str, part: Option<Part>) -> Option<Part> {
+        let previous = match part {
+            Some(part) => self.pointers.insert(seat.to_owned(), part),
+            None => self.pointers.remove(seat),
+        };
+        if previous != part {
+            // Releasing the button elsewhere does not count as a click.
+            if let Some(Part::Button(_)) = previous {
+                self.pressed = None;
+            }
+            // Buttons are highlighted under the pointer.
+            let is_button = |part: Option<Part>| matches!(part, Some(Part::Button(_)));
+            if is_button(previous) || is_button(part) {
+                handle.request_redraw();
+            }
+        }
+        previous
+    }
+
+    /// Finds the part of the window at `position` in surface coordinates.
+    fn hit_test(&self, position: Vector2F, window_size: Vector2I) -> Part {
+        if !self.client_side() {
+            return Part::Content;
+        }
+        let window = self.window_geometry(window_size).to_f32();
+        if !self.window_state.get().maximized {
+            let left = position.x() < window.min_x() + RESIZE_INSET;
+            let right = position.x() >= window.max_x() - RESIZE_INSET;
+            let top = position.y() < window.min_y() + RESIZE_INSET;
+            let bottom = position.y() >= window.max_y() - RESIZE_INSET;
+            // Corners extend a bit along both edges, so they are easy to hit.
+            let near_left = position.x() < window.min_x() + CORNER_SIZE;
+            let near_right = position.x() >= window.max_x() - CORNER_SIZE;
+            let near_top = position.y() < window.min_y() + CORNER_SIZE;
+            let near_bottom = position.y() >= window.max_y() - CORNER_SIZE;
+            let edge = if (top && near_left) || (left && near_top) {
+                Some(ResizeEdge::TopLeft)
+            } else if (top && near_right) || (right && near_top) {
+                Some(ResizeEdge::TopRight)
+            } else if (bottom && near_left) || (left && near_bottom) {
+                Some(ResizeEdge::BottomLeft)
+            } else if (bottom && near_right) || (right && near_bottom) {
+                Some(ResizeEdge::BottomRight)
+            } else if top {
+                Some(ResizeEdge::Top)
+            } else if bottom {
+                Some(ResizeEdge::Bottom)
+            } else if left {
+                Some(ResizeEdge::Left)
+            } else if right {
+                Some(ResizeEdge::Right)
+            } else {
+                None
+            };
+            if let Some(edge) = edge {
+                return Part::Edge(edge);
+            }
+        }
+        if self.content_rect(window_size).contains_point(position) {
+            return Part::Content;
+        }
+        BUTTONS
+            .iter()
+            .enumerate()
+            .find(|(index, _)| button_rect(window, *index).contains_point(position))
+            .map_or(Part::Title, |(_, button)| Part::Button(*button))
+    }
+
+    /// Draws the shadow, the title bar and the window background.
+    pub(crate) fn draw(&self, canvas: &mut CanvasRenderingContext2D, window_size: Vector2I) {
+        let window = self.window_geometry(window_size).to_f32();
+
+        // The window background doubles as the shape casting the shadow.
+        canvas.save();
+        if self.margin() > 0 {
+            canvas.set_shadow_blur(SHADOW_WIDTH as f32 / 2.0);
+            canvas.set_shadow_color(ColorU::new(0, 0, 0, 96));
+        }
+        canvas.set_fill_style(ColorU::white());
+        canvas.fill_rect(window);
+        canvas.restore();
+
+        if !self.client_side() {
+            return;
+        }
+
+        // Inactive windows have a lighter title bar.
+        let activated = self.window_state.get().activated;
+        let title_bar = RectF::new(window.origin(), Vector2F::new(window.width(), TITLE_HEIGHT as f32));
+        canvas.set_fill_style(if activated {
+            ColorU::new(224, 224, 224, 255)
+        } else {
+            ColorU::new(240, 240, 240, 255)
+        });
+        canvas.fill_rect(title_bar);
+
+        let hovered: Vec<Button> = self
+            .pointers
+            .values()
+            .filter_map(|part| match part {
+                Part::Button(button) => Some(*button),
+                _ => None,
+            })
+            .collect();
+        for (index, &button) in BUTTONS.iter().enumerate() {
+            let rect = button_rect(window, index);
+            if self.pressed == Some(button) {
+                canvas.set_fill_style(ColorU::new(160, 160, 160, 255));
+                canvas.fill_rect(rect);
+            } else if hovered.contains(&button) {
+                canvas.set_fill_style(ColorU::new(192, 192, 192, 255));
+                canvas.fill_rect(rect);
+            }
+            draw_button(canvas, button, rect.center());
+        }
+
+        // The title is centered in the space left of the buttons.
+        let title_width = window.width() - BUTTONS.len() as f32 * BUTTON_WIDTH;
+        canvas.set_fill_style(if activated {
+            ColorU::black()
+        } else {
+            ColorU::new(128, 128, 128, 255)
+        });
+        canvas.set_font_size(TITLE_FONT_SIZE);
+        canvas.set_text_align(TextAlign::Center);
+        canvas.set_text_baseline(TextBaseline::Middle);
+        canvas.fill_text(
+            &self.title,
+            title_bar.origin() + Vector2F::new(title_width / 2.0, TITLE_HEIGHT as f32 / 2.0),
+        );
+    }
+}
+
+/// Returns where the button with the given index is drawn.
+fn button_rect(window: RectF, index: usize) -> RectF {
+    let x = window.max_x() - (index + 1) as f32 * BUTTON_WIDTH;
+    RectF::new(
+        Vector2F::new(x, window.min_y()),
+        Vector2F::new(BUTTON_WIDTH, TITLE_HEIGHT as f32),
+    )
+}
+
+/// Draws the symbol of `button` around `center`.
+fn draw_button(canvas: &mut CanvasRenderingContext2D, button: Button, center: Vector2F) {
+    let half = ICON_SIZE / 2.0;
+    let mut path = Path2D::new();
+    match button {
+        Button::Close => {
+            path.move_to(center + Vector2F::new(-half, -half));
+            path.line_to(center + Vector2F::new(half, half));
+            path.move_to(center + Vector2F::new(half, -half));
+            path.line_to(center + Vector2F::new(-half, half));
+        }
+        Button::Maximize => {
+            path.rect(RectF::new(
+                center + Vector2F::new(-half, -half),
+                Vector2F::new(ICON_SIZE, ICON_SIZE),
+            ));
+        }
+        Button::Minimize => {
+            path.move_to(center + Vector2F::new(-half, half));
+            path.line_to(center + Vector2F::new(half, half));
+        }
+    }
+    canvas.set_line_width(1.5);
+    canvas.set_stroke_style(ColorU::black());
+    canvas.stroke_path(path);
+}
@@ -0,0 +1,156 @@
+//! Errors that can occur while setting up a window.
+
+use khronos_egl as egl;
+use std::fmt;
+use std::io;
+use wayland_client::{ConnectError, Display, GlobalManager};
+
+/// The reasons why bean could not open or keep a window.
+#[derive(Debug)]
+pub enum Error {
+    /// No Wayland compositor could be reached.
+    NoWaylandDisplay(ConnectError),
+    /// The connection to the compositor was lost, e.g. because the
+    /// compositor exited.
+    Connection(io::Error),
+    /// The compositor closed the connection because bean violated the
+    /// protocol.
+    Protocol {
+        /// The interface of the object that caused the error.
+        interface: &'static str,
+        /// The id of the object that caused the error.
+        object_id: u32,
+        /// The error code, its meaning depends on the interface.
+        code: u32,
+        /// The description sent by the compositor.
+        message: String,
+    },
+    /// A global required by bean is not advertised by the compositor.
+    MissingGlobal {
+        /// The name of the interface, e.g. `wl_compositor`.
+        interface: &'static str,
+        /// The minimal version bean needs.
+        version: u32,
+        /// All globals the compositor did advertise with their versions.
+        advertised: Vec<(String, u32)>,
+    },
+    /// `libwayland-egl` could not be loaded.
+    WaylandEglUnavailable,
+    /// The EGL display could not be initialized.
+    EglInit(Option<egl::Error>),
+    /// No EGL framebuffer configuration matches the requirements.
+    NoEglConfig,
+    /// The OpenGL context could not be created.
+    ContextCreation(egl::Error),
+    /// The EGL window surface could not be created or made current.
+    SurfaceCreation(egl::Error),
+    /// The Pathfinder shaders could not be compiled.
+    ShaderCompile(String),
+    /// A rendered frame could not be presented.
+    SwapBuffers(egl::Error),
+    /// The event loop could not be created or waiting for events failed.
+    EventLoop(io::Error),
+}
+
+impl Error {
+    /// Builds the error for a global the compositor does not provide.
+    pub(crate) fn missing_global(globals: &GlobalManager, interface: &'static str, version: u32) -> Error {
+        let mut advertised: Vec<_> = globals
+            .list()
+            .into_iter()
+            .map(|(_, interface, version)| (interface, version))
+            .collect();
+        advertised.sort();
+        Error::MissingGlobal {
+            interface,
+            version,
+            advertised,
+        }
+    }
+
+    /// Builds the error for a failure on the connection to the compositor.
+    ///
+    /// If the compositor closed the connection because of a protocol error,
+    /// it is reported instead of the I/O error.
+    pub(crate) fn connection(display: &Display, err: io::Error) -> Error {
+        match display.protocol_error() {
+            Some(err) => Error::Protocol {
+                interface: err.object_interface,
+                object_id: err.object_id,
+                code: err.code,
+                message: err.message,
+            },
+            None => Error::Connection(err),
+        }
+    }
+
+    /// Returns true if the error ended the connection to the compositor.
+    ///
+    /// A window can still be opened on a new connection.
+    pub fn is_disconnect(&self) -> bool {
+        match self {
+            Error::Connection(_) | Error::Protocol { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::NoWaylandDisplay(err) => Some(err),
+            Error::Connection(err) => Some(err),
+            Error::EventLoop(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoWaylandDisplay(err) => write!(f, "Could not connect to a Wayland compositor: {}", err),
+            Error::Connection(err) => write!(f, "The connection to the Wayland compositor failed: {}", err),
+            Error::Protocol {
+                interface,
+                object_id,
+                code,
+                message,
+            } => write!(
+                f,
+                "The Wayland compositor reported a protocol error {} on {}@{}: {}",
+                code, interface, object_id, message
+            ),
+            Error::MissingGlobal {
+                interface,
+                version,
+                advertised,
+            } => {
+                write!(
+                    f,
+                    "The compositor does not support {} version {}. Advertised globals:",
+                    interface, version
+                )?;
+                for (interface, version) in advertised {
+                    write!(f, "\n    {} (version {})", interface, version)?;
+                }
+                Ok(())
+            }
+            Error::WaylandEglUnavailable => f.write_str("Could not load libwayland-egl."),
+            Error::EglInit(Some(err)) => write!(f, "Could not initialize EGL: {}", err),
+            Error::EglInit(None) => f.write_str("Could not get an EGL display for the Wayland connection."),
+            Error::NoEglConfig => f.write_str("No EGL configuration with 8 bit RGB channels is available."),
+            Error::ContextCreation(err) => write!(f, "Could not create an OpenGL context: {}", err),
+            Error::SurfaceCreation(err) => write!(f, "Could not create an EGL window surface: {}", err),
+            Error::ShaderCompile(msg) => write!(f, "Could not compile the Pathfinder shaders: {}", msg),
+            Error::SwapBuffers(err) => write!(f, "Could not present a frame: {}", err),
+            Error::EventLoop(err) => write!(f, "The event loop failed: {}", err),
+        }
+    }
+}
+
+impl From<ConnectError> for Error {
+    fn from(err: ConnectError) -> Error {
+        Error::NoWaylandDisplay(err)
+    }
+}
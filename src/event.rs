@@ -0,0 +1,167 @@
+//! Input events delivered to applications.
+
+use crate::keyboard::KeyEvent;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+
+/// A mouse button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    /// The primary button.
+    Left,
+    /// The secondary button.
+    Right,
+    /// The wheel button.
+    Middle,
+    /// Any other button with its evdev code.
+    Other(u32),
+}
+
+impl From<u32> for MouseButton {
+    /// Converts an evdev button code as used by the Wayland protocol.
+    fn from(button: u32) -> MouseButton {
+        // see linux/input-event-codes.h
+        match button {
+            0x110 => MouseButton::Left,
+            0x111 => MouseButton::Right,
+            0x112 => MouseButton::Middle,
+            button => MouseButton::Other(button),
+        }
+    }
+}
+
+/// The device that caused a scroll event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrollSource {
+    /// A mouse wheel with discrete steps.
+    Wheel,
+    /// Fingers on a touchpad, followed by a stop event when they are lifted.
+    Finger,
+    /// A continuous device without a clear end, e.g. a trackpoint.
+    Continuous,
+    /// A mouse wheel tilted to the side.
+    WheelTilt,
+    /// The compositor did not report the source.
+    Unknown,
+}
+
+/// Scrolling along both axes within one pointer frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScrollEvent {
+    /// The scroll distance in logical pixels, positive values scroll down
+    /// and right.
+    pub delta: Vector2F,
+    /// The number of wheel steps in fractions of 120, one click is 120.
+    ///
+    /// Only reported for wheels, for other sources it is zero.
+    pub v120: Vector2I,
+    /// The device that scrolled.
+    pub source: ScrollSource,
+    /// Scrolling along the horizontal axis stopped.
+    ///
+    /// Sent for touchpads when the fingers are lifted, applications can
+    /// start kinetic scrolling now.
+    pub horizontal_stop: bool,
+    /// Scrolling along the vertical axis stopped.
+    pub vertical_stop: bool,
+    /// Time of the first scroll event in the frame.
+    pub time: u32,
+}
+
+/// A finger on a touchscreen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TouchPoint {
+    /// Identifies the touch point until it is lifted again.
+    pub id: i32,
+    /// The position in logical coordinates relative to the window contents.
+    pub position: Vector2F,
+    /// Major and minor axis of the contact ellipse in logical pixels.
+    ///
+    /// Only reported by some touchscreens.
+    pub shape: Option<Vector2F>,
+    /// Angle of the major axis of the contact ellipse in degrees, clockwise
+    /// from the vertical axis.
+    ///
+    /// Only reported by some touchscreens.
+    pub orientation: Option<f32>,
+}
+
+/// Something the user did with an input device.
+///
+/// Positions are in logical coordinates relative to the window contents,
+/// the same coordinate space the application draws in. Times are in
+/// milliseconds with an undefined base, they are only useful to compare
+/// events. Serials identify the event in requests to the compositor, e.g. to
+/// start moving the window.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    /// The pointer entered the window.
+    ///
+    /// The serial is `None` if bean made up the event, e.g. when the pointer
+    /// moves from the decorations to the contents.
+    PointerEnter {
+        position: Vector2F,
+        serial: Option<u32>,
+    },
+    /// The pointer left the window.
+    ///
+    /// The serial is `None` if bean made up the event, e.g. when the pointer
+    /// moves onto the decorations or an emulated pointer is cancelled.
+    PointerLeave {
+        serial: Option<u32>,
+    },
+    /// The pointer moved within the window.
+    PointerMotion {
+        position: Vector2F,
+        time: u32,
+    },
+    /// A mouse button was pressed or released.
+    PointerButton {
+        button: MouseButton,
+        pressed: bool,
+        time: u32,
+        serial: u32,
+    },
+    /// The user scrolled with a wheel or touchpad.
+    PointerScroll(ScrollEvent),
+    /// A key was pressed or is repeating.
+    KeyDown {
+        key: KeyEvent,
+        time: u32,
+        serial: u32,
+    },
+    /// A key was released.
+    KeyUp {
+        key: KeyEvent,
+        time: u32,
+        serial: u32,
+    },
+    /// A finger touched the screen.
+    TouchDown {
+        point: TouchPoint,
+        time: u32,
+        serial: u32,
+    },
+    /// A touch point moved or changed its shape.
+    TouchMotion {
+        point: TouchPoint,
+        time: u32,
+    },
+    /// A finger was lifted from the screen.
+    TouchUp {
+        id: i32,
+        time: u32,
+        serial: u32,
+    },
+    /// The compositor took over all touch points, e.g. for a gesture.
+    ///
+    /// All touch points are gone, no `TouchUp` events follow.
+    TouchCancel,
+    /// The window gained keyboard focus.
+    FocusIn {
+        serial: u32,
+    },
+    /// The window lost keyboard focus.
+    FocusOut {
+        serial: u32,
+    },
+}
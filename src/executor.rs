@@ -0,0 +1,421 @@
+//! Running futures on the thread of the window.
+//!
+//! Tasks are polled by the event loop of the window, between handling events
+//! and drawing. They are not `Send` and can share the application state with
+//! `Rc<RefCell<_>>`.
+//!
+//! All windows of a thread share the tasks. Whichever window is running
+//! polls them, so they keep making progress as long as one window is open.
+
+use crate::error::Error;
+use calloop::ping::{make_ping, Ping, PingSource};
+use calloop::{LoopHandle, Source};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::mem::{self, ManuallyDrop};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::time::{Duration, Instant};
+
+thread_local! {
+    static EXECUTOR: RefCell<Executor> = RefCell::new(Executor::default());
+}
+
+/// The parts of the executor wakers from other threads use.
+#[derive(Default)]
+struct Shared {
+    // Tasks to poll in the next run.
+    ready: Mutex<Vec<u64>>,
+    // Wake the event loops of the open windows, by registration id.
+    pings: Mutex<Vec<(u64, Ping)>>,
+}
+
+impl Shared {
+    fn wake(&self, task: u64) {
+        self.ready.lock().unwrap().push(task);
+        for (_, ping) in &*self.pings.lock().unwrap() {
+            ping.ping();
+        }
+    }
+}
+
+#[derive(Default)]
+struct Executor {
+    shared: Arc<Shared>,
+    // Tasks by their id, without the one currently polled.
+    tasks: HashMap<u64, Pin<Box<dyn Future<Output = ()>>>>,
+    next_task: u64,
+    // Sleeping futures by deadline and id.
+    timers: BTreeMap<(Instant, u64), Waker>,
+    next_timer: u64,
+    // Futures waiting for the next frame.
+    frame_waiters: Vec<Waker>,
+    // Counts the frames futures were woken for.
+    frame: u64,
+    next_registration: u64,
+}
+
+/// Runs `future` on the thread of the window.
+///
+/// The future is polled by the event loop of the window, it makes progress
+/// while [`Window::run`](crate::Window::run) runs. Futures spawned before
+/// the window is opened start once it is.
+pub fn spawn_local<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    let (task, shared) = EXECUTOR.with(|executor| {
+        let mut executor = executor.borrow_mut();
+        let task = executor.next_task;
+        executor.next_task += 1;
+        executor.tasks.insert(task, Box::pin(future));
+        (task, executor.shared.clone())
+    });
+    shared.wake(task);
+}
+
+/// Waits until the window is about to draw its next frame.
+///
+/// The window keeps drawing frames as long as a future waits for one, the
+/// state changed before the future returns is shown in the frame. Awaiting
+/// this in a loop animates the window in step with the display.
+pub fn next_frame() -> NextFrame {
+    NextFrame { frame: None }
+}
+
+/// Waits until `duration` has passed.
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: Instant::now() + duration,
+        timer: None,
+    }
+}
+
+/// The future returned by [`next_frame`].
+#[must_use = "futures do nothing unless awaited"]
+pub struct NextFrame {
+    // The frame counter when the waker was registered.
+    frame: Option<u64>,
+}
+
+impl Future for NextFrame {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        EXECUTOR.with(|executor| {
+            let mut executor = executor.borrow_mut();
+            match self.frame {
+                Some(frame) if frame < executor.frame => Poll::Ready(()),
+                _ => {
+                    self.frame = Some(executor.frame);
+                    executor.frame_waiters.push(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+/// The future returned by [`sleep`].
+#[must_use = "futures do nothing unless awaited"]
+pub struct Sleep {
+    deadline: Instant,
+    // The id of the registered timer.
+    timer: Option<u64>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        let deadline = self.deadline;
+        EXECUTOR.with(|executor| {
+            let mut executor = executor.borrow_mut();
+            let timer = match self.timer {
+                Some(timer) => timer,
+                None => {
+                    let timer = executor.next_timer;
+                    executor.next_timer += 1;
+                    timer
+                }
+            };
+            self.timer = Some(timer);
+            executor.timers.insert((deadline, timer), cx.waker().clone());
+        });
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(timer) = self.timer {
+            // The executor may already be gone when the thread exits.
+            let _ = EXECUTOR.try_with(|executor| executor.borrow_mut().timers.remove(&(self.deadline, timer)));
+        }
+    }
+}
+
+/// Lets the event loop of a window poll the tasks.
+///
+/// Returns the id to pass to [`unregister`] and the source, which must be
+/// removed before the event loop is dropped.
+pub(crate) fn register<A>(handle: &LoopHandle<A>) -> Result<(u64, Source<PingSource>), Error> {
+    let (ping, source) = make_ping().map_err(Error::EventLoop)?;
+    let source = handle
+        .insert_source(source, |_, _, _| run())
+        .map_err(|err| Error::EventLoop(err.into()))?;
+    let (id, shared) = EXECUTOR.with(|executor| {
+        let mut executor = executor.borrow_mut();
+        let id = executor.next_registration;
+        executor.next_registration += 1;
+        (id, executor.shared.clone())
+    });
+    // Tasks spawned without a window start now.
+    if !shared.ready.lock().unwrap().is_empty() {
+        ping.ping();
+    }
+    shared.pings.lock().unwrap().push((id, ping));
+    Ok((id, source))
+}
+
+/// Stops waking the event loop registered with `id`.
+///
+/// The event loops of other windows on the thread keep polling the tasks.
+pub(crate) fn unregister(id: u64) {
+    let _ = EXECUTOR.try_with(|executor| {
+        let executor = executor.borrow();
+        let mut pings = executor.shared.pings.lock().unwrap();
+        pings.retain(|&(registration, _)| registration != id);
+    });
+}
+
+/// Polls all tasks that were woken since the last run.
+pub(crate) fn run() {
+    let shared = EXECUTOR.with(|executor| executor.borrow().shared.clone());
+    let ready = mem::replace(&mut *shared.ready.lock().unwrap(), Vec::new());
+    for task in ready {
+        // Tasks woken twice or finished in the meantime are skipped.
+        let mut future = match EXECUTOR.with(|executor| executor.borrow_mut().tasks.remove(&task)) {
+            Some(future) => future,
+            None => continue,
+        };
+        let waker = task_waker(task, shared.clone());
+        if future.as_mut().poll(&mut Context::from_waker(&waker)).is_pending() {
+            EXECUTOR.with(|executor| executor.borrow_mut().tasks.insert(task, future));
+        }
+    }
+}
+
+/// Returns when the next sleeping future wakes up.
+pub(crate) fn next_timer() -> Option<Instant> {
+    EXECUTOR.with(|executor| executor.borrow().timers.keys().next().map(|&(deadline, _)| deadline))
+}
+
+/// Wakes all sleeping futures whose deadline passed.
+pub(crate) fn wake_timers() {
+    let now = Instant::now();
+    let expired: Vec<_> = EXECUTOR.with(|executor| {
+        let mut executor = executor.borrow_mut();
+        let expired: Vec<_> = executor.timers.range(..(now, u64::MAX)).map(|(&key, _)| key).collect();
+        expired
+            .into_iter()
+            .filter_map(|key| executor.timers.remove(&key))
+            .collect()
+    });
+    for waker in expired {
+        waker.wake();
+    }
+}
+
+/// Wakes all futures waiting for the next frame.
+///
+/// Returns false if no future was waiting.
+pub(crate) fn wake_frame_waiters() -> bool {
+    let waiters = EXECUTOR.with(|executor| {
+        let mut executor = executor.borrow_mut();
+        if !executor.frame_waiters.is_empty() {
+            executor.frame += 1;
+        }
+        mem::replace(&mut executor.frame_waiters, Vec::new())
+    });
+    let waiting = !waiters.is_empty();
+    for waker in waiters {
+        waker.wake();
+    }
+    waiting
+}
+
+/// Wakes a task from any thread.
+struct TaskWaker {
+    task: u64,
+    shared: Arc<Shared>,
+}
+
+const VTABLE: RawWakerVTable = RawWakerVTable::new(clone_waker, wake, wake_by_ref, drop_waker);
+
+fn task_waker(task: u64, shared: Arc<Shared>) -> Waker {
+    let data = Arc::into_raw(Arc::new(TaskWaker { task, shared }));
+    unsafe { Waker::from_raw(RawWaker::new(data as *const (), &VTABLE)) }
+}
+
+unsafe fn clone_waker(data: *const ()) -> RawWaker {
+    let waker = ManuallyDrop::new(Arc::from_raw(data as *const TaskWaker));
+    let clone = Arc::clone(&waker);
+    RawWaker::new(Arc::into_raw(clone) as *const (), &VTABLE)
+}
+
+unsafe fn wake(data: *const ()) {
+    let waker = Arc::from_raw(data as *const TaskWaker);
+    waker.shared.wake(waker.task);
+}
+
+unsafe fn wake_by_ref(data: *const ()) {
+    let waker = &*(data as *const TaskWaker);
+    waker.shared.wake(waker.task);
+}
+
+unsafe fn drop_waker(data: *const ()) {
+    drop(Arc::from_raw(data as *const TaskWaker));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calloop::EventLoop;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::thread;
+
+    // Every test runs on its own thread, so each has a fresh executor.
+
+    #[test]
+    fn spawned_tasks_run_in_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        for i in 0..3 {
+            let log = log.clone();
+            spawn_local(async move { log.borrow_mut().push(i) });
+        }
+        assert!(log.borrow().is_empty());
+        run();
+        assert_eq!(*log.borrow(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn sleep_wakes_after_deadline() {
+        let done = Rc::new(Cell::new(false));
+        let task = done.clone();
+        spawn_local(async move {
+            sleep(Duration::from_millis(50)).await;
+            task.set(true);
+        });
+        run();
+        let deadline = next_timer().expect("no timer registered");
+        wake_timers();
+        run();
+        assert!(!done.get());
+
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        wake_timers();
+        run();
+        assert!(done.get());
+        assert_eq!(next_timer(), None);
+    }
+
+    #[test]
+    fn sleeps_wake_in_deadline_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        for &(i, millis) in &[(0, 20), (1, 10)] {
+            let log = log.clone();
+            spawn_local(async move {
+                sleep(Duration::from_millis(millis)).await;
+                log.borrow_mut().push(i);
+            });
+        }
+        run();
+        thread::sleep(Duration::from_millis(10));
+        while log.borrow().len() < 2 {
+            wake_timers();
+            run();
+        }
+        assert_eq!(*log.borrow(), vec![1, 0]);
+    }
+
+    #[test]
+    fn next_frame_waits_for_frame() {
+        let frames = Rc::new(Cell::new(0));
+        let task = frames.clone();
+        spawn_local(async move {
+            loop {
+                next_frame().await;
+                task.set(task.get() + 1);
+            }
+        });
+        run();
+        assert_eq!(frames.get(), 0);
+        run();
+        assert_eq!(frames.get(), 0);
+
+        assert!(wake_frame_waiters());
+        run();
+        assert_eq!(frames.get(), 1);
+        assert!(wake_frame_waiters());
+        run();
+        assert_eq!(frames.get(), 2);
+    }
+
+    #[test]
+    fn no_frame_without_waiters() {
+        assert!(!wake_frame_waiters());
+        spawn_local(async {});
+        run();
+        assert!(!wake_frame_waiters());
+    }
+
+    #[test]
+    fn frame_then_sleep() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let task = log.clone();
+        spawn_local(async move {
+            next_frame().await;
+            task.borrow_mut().push("frame");
+            sleep(Duration::from_millis(0)).await;
+            task.borrow_mut().push("sleep");
+            next_frame().await;
+            task.borrow_mut().push("second frame");
+        });
+        run();
+        assert!(log.borrow().is_empty());
+        wake_frame_waiters();
+        run();
+        assert_eq!(*log.borrow(), vec!["frame", "sleep"]);
+        wake_frame_waiters();
+        run();
+        assert_eq!(*log.borrow(), vec!["frame", "sleep", "second frame"]);
+    }
+
+    #[test]
+    fn unregister_keeps_other_windows() {
+        let mut first: EventLoop<()> = EventLoop::new().unwrap();
+        let mut second: EventLoop<()> = EventLoop::new().unwrap();
+        let (first_id, first_source) = register(&first.handle()).unwrap();
+        let (second_id, second_source) = register(&second.handle()).unwrap();
+        unregister(first_id);
+        first.handle().remove(first_source);
+
+        let done = Rc::new(Cell::new(false));
+        let task = done.clone();
+        spawn_local(async move { task.set(true) });
+        first.dispatch(Some(Duration::from_millis(0)), &mut ()).unwrap();
+        assert!(!done.get());
+        second.dispatch(Some(Duration::from_millis(0)), &mut ()).unwrap();
+        assert!(done.get());
+
+        unregister(second_id);
+        second.handle().remove(second_source);
+    }
+}
@@ -0,0 +1,221 @@
+//! Rendering without a Wayland compositor.
+//!
+//! Uses the surfaceless EGL platform of Mesa if it is available, so it runs
+//! on machines without a display or GPU. Set `LIBGL_ALWAYS_SOFTWARE=1` to
+//! force the llvmpipe software rasterizer.
+
+use crate::context::{create_with_renderer, GlVersion};
+use crate::error::Error;
+use crate::image::Image;
+use crate::render;
+use khronos_egl::{self as egl, Context as EGLContext, Display as EGLDisplay};
+use pathfinder_canvas::{CanvasFontContext, CanvasRenderingContext2D};
+use pathfinder_color::ColorF;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gl::GLDevice;
+use pathfinder_renderer::gpu::renderer::Renderer;
+use std::ffi::c_void;
+use std::mem::ManuallyDrop;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// `EGL_PLATFORM_SURFACELESS_MESA` from `EGL_MESA_platform_surfaceless`.
+const PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+
+/// The number of instances sharing the EGL display.
+static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+type GetPlatformDisplayExt =
+    unsafe extern "C" fn(platform: u32, native_display: *mut c_void, attrib_list: *const i32) -> *mut c_void;
+
+/// Renders canvases into an offscreen buffer.
+///
+/// ```no_run
+/// use bean::Headless;
+/// use pathfinder_geometry::rect::RectF;
+/// use pathfinder_geometry::vector::{Vector2F, Vector2I};
+///
+/// let mut headless = Headless::new(Vector2I::new(320, 240)).unwrap();
+/// let image = headless.render(|canvas| {
+///     canvas.fill_rect(RectF::new(Vector2F::new(10.0, 10.0), Vector2F::new(100.0, 50.0)));
+/// }).unwrap();
+/// image.save_png("rect.png").unwrap();
+/// ```
+pub struct Headless {
+    display: EGLDisplay,
+    context: EGLContext,
+    surface: egl::Surface,
+    // Dropped while the context is still alive.
+    renderer: ManuallyDrop<Renderer<GLDevice>>,
+    font_context: CanvasFontContext,
+    size: Vector2I,
+    gl_version: GlVersion,
+}
+
+impl Headless {
+    /// Creates an offscreen OpenGL context with a buffer of the given size.
+    pub fn new(size: Vector2I) -> Result<Headless, Error> {
+        let display = match surfaceless_display() {
+            Some(display) => display,
+            None => {
+                let default_display = unsafe { egl::NativeDisplayType::from_ptr(std::ptr::null_mut()) };
+                egl::get_display(default_display).ok_or(Error::EglInit(None))?
+            }
+        };
+        egl::initialize(display).map_err(|err| Error::EglInit(Some(err)))?;
+        INSTANCES.fetch_add(1, Ordering::SeqCst);
+        Headless::with_display(display, size).map_err(|err| {
+            release(display);
+            err
+        })
+    }
+
+    /// Creates the context and renderer on an initialized display.
+    fn with_display(display: EGLDisplay, size: Vector2I) -> Result<Headless, Error> {
+        let pbuffer_attributes = [
+            egl::WIDTH, size.x(),
+            egl::HEIGHT, size.y(),
+            egl::NONE,
+        ];
+        let (context, _, surface, gl_version, renderer) =
+            create_with_renderer(display, egl::PBUFFER_BIT, size, ColorF::white(), |config| {
+                egl::create_pbuffer_surface(display, config, &pbuffer_attributes).map_err(Error::SurfaceCreation)
+            })?;
+        Ok(Headless {
+            display,
+            context,
+            surface,
+            renderer: ManuallyDrop::new(renderer),
+            font_context: CanvasFontContext::from_system_source(),
+            size,
+            gl_version,
+        })
+    }
+
+    /// Returns the size of the rendered images.
+    pub fn size(&self) -> Vector2I {
+        self.size
+    }
+
+    /// Returns the OpenGL flavor that was negotiated with the driver.
+    pub fn gl_version(&self) -> GlVersion {
+        self.gl_version
+    }
+
+    /// Draws a canvas with `draw` and returns the rendered pixels.
+    ///
+    /// The context that was current on the thread before, e.g. the one of a
+    /// window, is current again afterwards.
+    pub fn render<F>(&mut self, draw: F) -> Result<Image, Error>
+    where
+        F: FnOnce(&mut CanvasRenderingContext2D),
+    {
+        let previous = CurrentContext::get();
+        egl::make_current(self.display, Some(self.surface), Some(self.surface), Some(self.context))
+            .map_err(Error::SurfaceCreation)?;
+        let mut canvas = CanvasRenderingContext2D::new(self.font_context.clone(), self.size.to_f32());
+        draw(&mut canvas);
+        render::render_canvas(canvas, &mut self.renderer);
+        let image = read_pixels(self.size);
+        previous.restore(self.display);
+        Ok(image)
+    }
+}
+
+impl Drop for Headless {
+    fn drop(&mut self) {
+        let previous = CurrentContext::get();
+        // Pathfinder frees its GPU resources when dropped, which only works
+        // while the context is current.
+        let _ = egl::make_current(self.display, Some(self.surface), Some(self.surface), Some(self.context));
+        unsafe {
+            ManuallyDrop::drop(&mut self.renderer);
+        }
+        let _ = egl::make_current(self.display, None, None, None);
+        let _ = egl::destroy_surface(self.display, self.surface);
+        let _ = egl::destroy_context(self.display, self.context);
+        previous.restore(self.display);
+        release(self.display);
+    }
+}
+
+/// The context current on this thread, saved while a headless context is
+/// used.
+struct CurrentContext {
+    display: Option<EGLDisplay>,
+    draw: Option<egl::Surface>,
+    read: Option<egl::Surface>,
+    context: Option<EGLContext>,
+}
+
+impl CurrentContext {
+    fn get() -> CurrentContext {
+        CurrentContext {
+            display: egl::get_current_display(),
+            draw: egl::get_current_surface(egl::DRAW),
+            read: egl::get_current_surface(egl::READ),
+            context: egl::get_current_context(),
+        }
+    }
+
+    /// Makes the saved context current again, or none if there was none.
+    ///
+    /// `display` releases the headless context if nothing was current.
+    fn restore(self, display: EGLDisplay) {
+        let _ = match (self.display, self.context) {
+            (Some(previous), Some(context)) => egl::make_current(previous, self.draw, self.read, Some(context)),
+            _ => egl::make_current(display, None, None, None),
+        };
+    }
+}
+
+/// Terminates the display once no instance uses it anymore.
+///
+/// EGL returns the same display to every instance, terminating it would
+/// invalidate the contexts of all of them.
+fn release(display: EGLDisplay) {
+    if INSTANCES.fetch_sub(1, Ordering::SeqCst) == 1 {
+        let _ = egl::terminate(display);
+    }
+}
+
+/// Returns a display of the surfaceless platform if Mesa provides one.
+fn surfaceless_display() -> Option<EGLDisplay> {
+    let get_platform_display = egl::get_proc_address("eglGetPlatformDisplayEXT")?;
+    let get_platform_display: GetPlatformDisplayExt = unsafe { std::mem::transmute(get_platform_display) };
+    let attributes = [egl::NONE];
+    let display = unsafe {
+        get_platform_display(PLATFORM_SURFACELESS_MESA, std::ptr::null_mut(), attributes.as_ptr())
+    };
+    if display.is_null() {
+        return None;
+    }
+    Some(unsafe { EGLDisplay::from_ptr(display) })
+}
+
+/// Reads the default framebuffer of the current context.
+fn read_pixels(size: Vector2I) -> Image {
+    let (width, height) = (size.x() as usize, size.y() as usize);
+    let mut data = vec![0; width * height * 4];
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data.as_mut_ptr() as *mut c_void,
+        );
+    }
+
+    // OpenGL stores the bottom row first.
+    let stride = width * 4;
+    let data = data.chunks(stride).rev().flatten().copied().collect();
+    Image {
+        width: width as u32,
+        height: height as u32,
+        data,
+    }
+}
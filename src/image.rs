@@ -0,0 +1,65 @@
+//! Rendered images and their PNG encoding.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+/// An image with 8 bit RGBA pixels.
+///
+/// Rows are stored from top to bottom without padding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// The pixels, four bytes each.
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    /// Returns the RGBA value of the pixel at `x`, `y`.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [self.data[i], self.data[i + 1], self.data[i + 2], self.data[i + 3]]
+    }
+
+    /// Writes the image to a PNG file.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        Ok(())
+    }
+
+    /// Reads an image from a PNG file.
+    ///
+    /// Only 8 bit RGB and RGBA images are supported.
+    pub fn load_png<P: AsRef<Path>>(path: P) -> io::Result<Image> {
+        let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        let (info, mut reader) = decoder.read_info()?;
+        let mut buf = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buf)?;
+        let data = match (info.color_type, info.bit_depth) {
+            (png::ColorType::RGBA, png::BitDepth::Eight) => buf,
+            (png::ColorType::RGB, png::BitDepth::Eight) => buf
+                .chunks(3)
+                .flat_map(|rgb| vec![rgb[0], rgb[1], rgb[2], 0xff])
+                .collect(),
+            (color_type, bit_depth) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported PNG format {:?} with {:?}", color_type, bit_depth),
+                ))
+            }
+        };
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            data,
+        })
+    }
+}
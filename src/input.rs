@@ -0,0 +1,358 @@
+//! The seats of the compositor and their input devices.
+//!
+//! Seats can appear and disappear at any time, e.g. when a second keyboard
+//! and mouse are assigned to their own seat. Every seat is tracked with the
+//! devices it currently has, and events are reported with the seat name.
+
+use crate::cursor::{CursorShape, Cursors};
+use crate::event::InputEvent;
+use crate::keyboard::Keyboard;
+use crate::pointer::Pointer;
+use crate::protocols::cursor_shape_v1::client::{wp_cursor_shape_device_v1, wp_cursor_shape_manager_v1};
+use crate::touch::Touch;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::{Rc, Weak};
+use std::time::Instant;
+use wayland_client::protocol::wl_seat::{self, Capability};
+use wayland_client::protocol::{wl_compositor, wl_keyboard, wl_pointer, wl_registry, wl_shm, wl_surface, wl_touch};
+use wayland_client::{Attached, Filter, GlobalEvent, Main};
+
+// declare an event enum containing the events we want to receive in the iterator
+event_enum!(
+    Events |
+    Pointer => wl_pointer::WlPointer,
+    Keyboard => wl_keyboard::WlKeyboard,
+    Touch => wl_touch::WlTouch
+);
+
+/// The highest `wl_seat` version bean understands.
+///
+/// Version 5 groups pointer events into frames and describes the source of
+/// scroll events, version 6 adds the shape of touch points.
+const SEAT_VERSION: u32 = 6;
+
+/// A seat with its input devices and their state.
+struct Seat {
+    wl_seat: Main<wl_seat::WlSeat>,
+    name: String,
+    pointer: Option<(Main<wl_pointer::WlPointer>, Pointer)>,
+    keyboard: Option<(Main<wl_keyboard::WlKeyboard>, Keyboard)>,
+    touch: Option<(Main<wl_touch::WlTouch>, Touch)>,
+    // Shows the cursor of the pointer, created when it is first needed.
+    cursor_surface: Option<Main<wl_surface::WlSurface>>,
+    // Sets the cursor shape of the pointer if the compositor draws cursors.
+    cursor_device: Option<Main<wp_cursor_shape_device_v1::WpCursorShapeDeviceV1>>,
+}
+
+impl Seat {
+    /// Creates and releases devices to match the capabilities of the seat.
+    fn set_capabilities(&mut self, capabilities: Capability, filter: &Filter<Events>, touch_emulates_pointer: bool) {
+        let wl_seat = &self.wl_seat;
+        update_device(&mut self.pointer, capabilities.contains(Capability::Pointer), || {
+            let pointer = wl_seat.get_pointer();
+            pointer.assign(filter.clone());
+            (pointer, Pointer::default())
+        });
+        if self.pointer.is_none() {
+            if let Some(device) = self.cursor_device.take() {
+                device.destroy();
+            }
+        }
+        update_device(&mut self.keyboard, capabilities.contains(Capability::Keyboard), || {
+            let keyboard = wl_seat.get_keyboard();
+            keyboard.assign(filter.clone());
+            (keyboard, Keyboard::new())
+        });
+        update_device(&mut self.touch, capabilities.contains(Capability::Touch), || {
+            let wl_touch = wl_seat.get_touch();
+            wl_touch.assign(filter.clone());
+            let mut touch = Touch::default();
+            touch.emulate_pointer = touch_emulates_pointer;
+            (wl_touch, touch)
+        });
+    }
+
+    /// Releases all devices and the seat itself.
+    fn release(mut self) {
+        // Also destroys the cursor shape device.
+        self.set_capabilities(Capability::empty(), &Filter::new(|_: Events, _, _| {}), false);
+        if let Some(surface) = self.cursor_surface.take() {
+            surface.destroy();
+        }
+        if self.wl_seat.as_ref().version() >= 5 {
+            self.wl_seat.release();
+        }
+    }
+}
+
+/// Input devices that can be released with `release`.
+trait Device {
+    fn release_device(&self);
+}
+
+macro_rules! impl_device {
+    ($($ty:ty),*) => {
+        $(
+            impl Device for Main<$ty> {
+                fn release_device(&self) {
+                    // The release request was added in version 3.
+                    if self.as_ref().version() >= 3 {
+                        self.release();
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_device!(wl_pointer::WlPointer, wl_keyboard::WlKeyboard, wl_touch::WlTouch);
+
+fn update_device<D: Device, S, F>(device: &mut Option<(D, S)>, available: bool, create: F)
+where
+    F: FnOnce() -> (D, S),
+{
+    match device {
+        None if available => *device = Some(create()),
+        Some((proxy, _)) if !available => {
+            proxy.release_device();
+            *device = None;
+        }
+        _ => {}
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    // Seats by the id of their global.
+    seats: HashMap<u32, Seat>,
+    // Protocol events waiting to be translated and handed to the application.
+    events: VecDeque<(u32, Events)>,
+    touch_emulates_pointer: bool,
+    // Needed to create cursor surfaces.
+    compositor: Option<Main<wl_compositor::WlCompositor>>,
+    cursors: Option<Cursors>,
+    // Preferred over the cursor theme if the compositor supports it.
+    cursor_shape_manager: Option<Main<wp_cursor_shape_manager_v1::WpCursorShapeManagerV1>>,
+}
+
+/// All seats of the compositor.
+///
+/// Handles are cheap to clone, they share the same seats.
+#[derive(Clone, Default)]
+pub(crate) struct Seats {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Seats {
+    /// Binds new seats and releases removed ones.
+    ///
+    /// Called for every global announced by the registry.
+    pub(crate) fn handle_global(&self, event: &GlobalEvent, registry: &Attached<wl_registry::WlRegistry>) {
+        match *event {
+            GlobalEvent::New { id, ref interface, version } if interface == "wl_seat" => {
+                let wl_seat = registry.bind::<wl_seat::WlSeat>(version.min(SEAT_VERSION), id);
+                let inner = Rc::downgrade(&self.inner);
+                let filter = event_filter(inner.clone(), id);
+                wl_seat.quick_assign(move |_, event, _| {
+                    let inner = match inner.upgrade() {
+                        Some(inner) => inner,
+                        None => return,
+                    };
+                    let mut inner = inner.borrow_mut();
+                    let touch_emulates_pointer = inner.touch_emulates_pointer;
+                    let seat = match inner.seats.get_mut(&id) {
+                        Some(seat) => seat,
+                        None => return,
+                    };
+                    // The capabilities of a seat are known at runtime and we retrieve
+                    // them via an events. 3 capabilities exists: pointer, keyboard, and touch
+                    match event {
+                        wl_seat::Event::Capabilities { capabilities } => {
+                            seat.set_capabilities(capabilities, &filter, touch_emulates_pointer);
+                        }
+                        wl_seat::Event::Name { name } => seat.name = name,
+                        _ => {}
+                    }
+                });
+                let seat = Seat {
+                    wl_seat,
+                    name: String::new(),
+                    pointer: None,
+                    keyboard: None,
+                    touch: None,
+                    cursor_surface: None,
+                    cursor_device: None,
+                };
+                self.inner.borrow_mut().seats.insert(id, seat);
+            }
+            GlobalEvent::Removed { id, ref interface } if interface == "wl_seat" => {
+                let mut inner = self.inner.borrow_mut();
+                if let Some(seat) = inner.seats.remove(&id) {
+                    seat.release();
+                }
+                // Events of a removed seat are meaningless.
+                inner.events.retain(|(seat, _)| *seat != id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Releases all seats and their devices.
+    ///
+    /// Only the settings are kept, the seats can be used for a new
+    /// connection afterwards.
+    pub(crate) fn release(&self) {
+        let mut inner = self.inner.borrow_mut();
+        for (_, seat) in inner.seats.drain() {
+            seat.release();
+        }
+        inner.events.clear();
+        inner.compositor = None;
+        inner.cursors = None;
+        if let Some(manager) = inner.cursor_shape_manager.take() {
+            manager.destroy();
+        }
+    }
+
+    /// Enables cursors, without a cursor theme the compositor picks the cursor.
+    pub(crate) fn init_cursors(&self, compositor: Main<wl_compositor::WlCompositor>, shm: &Attached<wl_shm::WlShm>) {
+        let mut inner = self.inner.borrow_mut();
+        inner.compositor = Some(compositor);
+        inner.cursors = Some(Cursors::load(shm));
+    }
+
+    /// Lets the compositor draw cursors instead of using the cursor theme.
+    pub(crate) fn init_cursor_shapes(&self, manager: Main<wp_cursor_shape_manager_v1::WpCursorShapeManagerV1>) {
+        self.inner.borrow_mut().cursor_shape_manager = Some(manager);
+    }
+
+    /// Shows `shape` for all pointers over the window.
+    ///
+    /// Cursor images are drawn for outputs with the given scale.
+    pub(crate) fn update_cursors(&self, shape: CursorShape, scale: i32) {
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+        if let Some(manager) = &inner.cursor_shape_manager {
+            for seat in inner.seats.values_mut() {
+                let (wl_pointer, pointer) = match &mut seat.pointer {
+                    Some(pointer) => pointer,
+                    None => continue,
+                };
+                let serial = match pointer.enter_serial {
+                    Some(serial) if pointer.cursor != Some(shape) => serial,
+                    _ => continue,
+                };
+                let device = seat.cursor_device.get_or_insert_with(|| manager.get_pointer(wl_pointer));
+                device.set_shape(serial, shape.wp_shape());
+                pointer.cursor = Some(shape);
+            }
+            return;
+        }
+
+        let (compositor, cursors) = match (&inner.compositor, &mut inner.cursors) {
+            (Some(compositor), Some(cursors)) => (compositor, cursors),
+            _ => return,
+        };
+        // Buffer scales need version 3 of the compositor.
+        let scale = if compositor.as_ref().version() >= 3 { scale } else { 1 };
+        if cursors.set_scale(scale) {
+            // Cursors that are already shown are drawn again at the new scale.
+            for seat in inner.seats.values_mut() {
+                if let Some((_, pointer)) = &mut seat.pointer {
+                    pointer.cursor = None;
+                }
+            }
+        }
+        for seat in inner.seats.values_mut() {
+            let (wl_pointer, pointer) = match &mut seat.pointer {
+                Some(pointer) => pointer,
+                None => continue,
+            };
+            let serial = match pointer.enter_serial {
+                Some(serial) if pointer.cursor != Some(shape) => serial,
+                _ => continue,
+            };
+            let surface = seat.cursor_surface.get_or_insert_with(|| compositor.create_surface());
+            // Remember missing shapes too, so they are not looked up every frame.
+            cursors.apply(wl_pointer, surface, serial, shape);
+            pointer.cursor = Some(shape);
+        }
+    }
+
+    /// Returns the seat with the given name.
+    pub(crate) fn wl_seat(&self, name: &str) -> Option<wl_seat::WlSeat> {
+        let inner = self.inner.borrow();
+        let seat = inner.seats.values().find(|seat| seat.name == name)?;
+        Some((**seat.wl_seat).clone())
+    }
+
+    /// Also reports the first finger on a touchscreen as a pointer.
+    pub(crate) fn set_touch_emulates_pointer(&self, enabled: bool) {
+        let mut inner = self.inner.borrow_mut();
+        inner.touch_emulates_pointer = enabled;
+        for seat in inner.seats.values_mut() {
+            if let Some((_, touch)) = &mut seat.touch {
+                touch.emulate_pointer = enabled;
+            }
+        }
+    }
+
+    /// Returns when a held key has to be repeated next.
+    pub(crate) fn next_repeat(&self) -> Option<Instant> {
+        let inner = self.inner.borrow();
+        inner
+            .seats
+            .values()
+            .filter_map(|seat| seat.keyboard.as_ref()?.1.next_repeat())
+            .min()
+    }
+
+    /// Translates the queued protocol events and due key repeats.
+    ///
+    /// Every input event is returned with the name of its seat.
+    pub(crate) fn take_events(&self, now: Instant) -> Vec<(String, InputEvent)> {
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+        let mut input_events = Vec::new();
+        for (id, event) in inner.events.drain(..) {
+            let seat = match inner.seats.get_mut(&id) {
+                Some(seat) => seat,
+                None => continue,
+            };
+            let events: Vec<_> = match event {
+                Events::Pointer { event, object } => match &mut seat.pointer {
+                    Some((_, pointer)) => pointer.handle(event, object.as_ref().version()).into_iter().collect(),
+                    None => continue,
+                },
+                Events::Keyboard { event, .. } => match &mut seat.keyboard {
+                    Some((_, keyboard)) => keyboard.handle(event).into_iter().collect(),
+                    None => continue,
+                },
+                Events::Touch { event, .. } => match &mut seat.touch {
+                    Some((_, touch)) => touch.handle(event),
+                    None => continue,
+                },
+            };
+            input_events.extend(events.into_iter().map(|event| (seat.name.clone(), event)));
+        }
+
+        for seat in inner.seats.values_mut() {
+            if let Some((_, keyboard)) = &mut seat.keyboard {
+                if let Some(event) = keyboard.repeat(now) {
+                    input_events.push((seat.name.clone(), event));
+                }
+            }
+        }
+        input_events
+    }
+}
+
+/// Returns a filter that queues the events of all devices of a seat.
+fn event_filter(inner: Weak<RefCell<Inner>>, seat: u32) -> Filter<Events> {
+    Filter::new(move |event, _, _| {
+        if let Some(inner) = inner.upgrade() {
+            inner.borrow_mut().events.push_back((seat, event));
+        }
+    })
+}
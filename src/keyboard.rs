@@ -0,0 +1,383 @@
+//! Keyboard input translated with xkbcommon.
+//!
+//! The compositor sends the keymap of the keyboard and raw key codes, the
+//! client is responsible for turning them into keysyms and text.
+
+use crate::event::InputEvent;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::FromRawFd;
+use std::time::{Duration, Instant};
+use wayland_client::protocol::wl_keyboard::{self, KeyState, KeymapFormat};
+use xkbcommon::xkb;
+
+pub use xkbcommon::xkb::keysyms;
+
+/// A key symbol, see [`keysyms`] for the possible values.
+pub type Keysym = u32;
+
+/// The state of the modifier keys.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    /// The Control key is held.
+    pub ctrl: bool,
+    /// The Alt key is held.
+    pub alt: bool,
+    /// The Shift key is held.
+    pub shift: bool,
+    /// The Super or Windows key is held.
+    pub logo: bool,
+    /// Caps Lock is active.
+    pub caps_lock: bool,
+    /// Num Lock is active.
+    pub num_lock: bool,
+}
+
+/// A key press or release, see [`InputEvent::KeyDown`] and [`InputEvent::KeyUp`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The symbol of the key in the current layout.
+    pub keysym: Keysym,
+    /// The text typed by the key, if any.
+    ///
+    /// Control characters, e.g. for Ctrl+S, are not reported as text.
+    pub text: Option<String>,
+    /// The modifiers active when the key was pressed.
+    pub modifiers: Modifiers,
+    /// True if the event was generated by key repeat.
+    pub repeat: bool,
+}
+
+/// Repeat rate and delay used until the compositor sends its own.
+const DEFAULT_REPEAT_INFO: RepeatInfo = RepeatInfo { rate: 25, delay: 600 };
+
+/// How fast held keys repeat.
+#[derive(Clone, Copy, Debug)]
+struct RepeatInfo {
+    /// Repeated events per second, zero disables key repeat.
+    rate: i32,
+    /// Milliseconds between the key press and the first repeat.
+    delay: i32,
+}
+
+/// The key that is currently repeating.
+struct Repeat {
+    key: u32,
+    next: Instant,
+    // Repeated events reuse the serial of the press and extrapolate its time.
+    serial: u32,
+    time: u32,
+    pressed_at: Instant,
+}
+
+/// Keeps the keymap and the modifier state of a keyboard.
+///
+/// Wayland leaves key repeat to the client, so the keyboard also tracks the
+/// held key and when it has to be repeated next.
+pub(crate) struct Keyboard {
+    context: xkb::Context,
+    state: Option<xkb::State>,
+    modifiers: Modifiers,
+    repeat_info: RepeatInfo,
+    repeat: Option<Repeat>,
+}
+
+impl Keyboard {
+    pub(crate) fn new() -> Keyboard {
+        Keyboard {
+            context: xkb::Context::new(xkb::CONTEXT_NO_FLAGS),
+            state: None,
+            modifiers: Modifiers::default(),
+            repeat_info: DEFAULT_REPEAT_INFO,
+            repeat: None,
+        }
+    }
+
+    /// Processes a keyboard event and returns the input event it describes.
+    pub(crate) fn handle(&mut self, event: wl_keyboard::Event) -> Option<InputEvent> {
+        match event {
+            wl_keyboard::Event::Keymap { format, fd, size } => {
+                // Take ownership of the file descriptor so it is closed again.
+                let file = unsafe { File::from_raw_fd(fd) };
+                if format == KeymapFormat::XkbV1 {
+                    self.load_keymap(file, size);
+                }
+                None
+            }
+            wl_keyboard::Event::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+                ..
+            } => {
+                if let Some(state) = &mut self.state {
+                    state.update_mask(mods_depressed, mods_latched, mods_locked, 0, 0, group);
+                    self.modifiers = modifiers(state);
+                }
+                None
+            }
+            wl_keyboard::Event::Key {
+                serial,
+                time,
+                key,
+                state,
+            } => {
+                if state == KeyState::Pressed {
+                    self.start_repeat(key, serial, time, Instant::now());
+                    Some(InputEvent::KeyDown {
+                        key: self.key_event(key, true, false),
+                        time,
+                        serial,
+                    })
+                } else {
+                    if self.repeat.as_ref().map_or(false, |repeat| repeat.key == key) {
+                        self.repeat = None;
+                    }
+                    Some(InputEvent::KeyUp {
+                        key: self.key_event(key, false, false),
+                        time,
+                        serial,
+                    })
+                }
+            }
+            wl_keyboard::Event::RepeatInfo { rate, delay } => {
+                self.repeat_info = RepeatInfo { rate, delay };
+                if rate <= 0 {
+                    self.repeat = None;
+                }
+                None
+            }
+            wl_keyboard::Event::Enter { serial, .. } => Some(InputEvent::FocusIn { serial }),
+            wl_keyboard::Event::Leave { serial, .. } => {
+                // Keys are not repeated for unfocused windows.
+                self.repeat = None;
+                Some(InputEvent::FocusOut { serial })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns when the held key has to be repeated next.
+    pub(crate) fn next_repeat(&self) -> Option<Instant> {
+        self.repeat.as_ref().map(|repeat| repeat.next)
+    }
+
+    /// Returns the repeated key event if it is due at `now`.
+    pub(crate) fn repeat(&mut self, now: Instant) -> Option<InputEvent> {
+        let interval = Duration::from_millis(1000 / self.repeat_info.rate.max(1) as u64);
+        let (key, time, serial) = {
+            let repeat = self.repeat.as_mut().filter(|repeat| repeat.next <= now)?;
+            repeat.next += interval;
+            // Skip repeats that were missed while the event loop was busy.
+            if repeat.next <= now {
+                repeat.next = now + interval;
+            }
+            let elapsed = now.duration_since(repeat.pressed_at).as_millis() as u32;
+            (repeat.key, repeat.time.wrapping_add(elapsed), repeat.serial)
+        };
+        Some(InputEvent::KeyDown {
+            key: self.key_event(key, true, true),
+            time,
+            serial,
+        })
+    }
+
+    /// Starts repeating `key`, which was pressed at `now`.
+    fn start_repeat(&mut self, key: u32, serial: u32, time: u32, now: Instant) {
+        let repeats = match &self.state {
+            Some(state) => state.get_keymap().key_repeats(key + 8),
+            None => false,
+        };
+        self.repeat = if repeats && self.repeat_info.rate > 0 {
+            Some(Repeat {
+                key,
+                next: now + Duration::from_millis(self.repeat_info.delay.max(0) as u64),
+                serial,
+                time,
+                pressed_at: now,
+            })
+        } else {
+            None
+        };
+    }
+
+    /// Builds the event for a key in the current keyboard state.
+    ///
+    /// `key` is the evdev key code as sent by the compositor.
+    fn key_event(&self, key: u32, pressed: bool, repeat: bool) -> KeyEvent {
+        // xkb key codes are offset by 8 from the evdev key codes
+        let keycode = key + 8;
+        let (keysym, text) = match &self.state {
+            Some(state) => {
+                let text = state.key_get_utf8(keycode);
+                let text = if pressed && !text.is_empty() && !text.chars().all(char::is_control) {
+                    Some(text)
+                } else {
+                    None
+                };
+                (state.key_get_one_sym(keycode), text)
+            }
+            None => (keysyms::KEY_NoSymbol, None),
+        };
+        KeyEvent {
+            keysym,
+            text,
+            modifiers: self.modifiers,
+            repeat,
+        }
+    }
+
+    fn load_keymap(&mut self, file: File, size: u32) {
+        // Compositors may send the same open file every time, its offset is
+        // shared, so the keymap is read from the start.
+        let mut keymap = vec![0; size as usize];
+        if file.read_exact_at(&mut keymap, 0).is_err() {
+            return;
+        }
+        if let Ok(keymap) = String::from_utf8(keymap) {
+            self.set_keymap(keymap);
+        }
+    }
+
+    fn set_keymap(&mut self, keymap: String) {
+        // The keymap is sent as a null terminated string.
+        let keymap = keymap.trim_end_matches('\0').to_owned();
+        let keymap = xkb::Keymap::new_from_string(
+            &self.context,
+            keymap,
+            xkb::KEYMAP_FORMAT_TEXT_V1,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        );
+        if let Some(keymap) = keymap {
+            self.state = Some(xkb::State::new(&keymap));
+            self.modifiers = Modifiers::default();
+        }
+    }
+}
+
+fn modifiers(state: &xkb::State) -> Modifiers {
+    let active = |name: &str| state.mod_name_is_active(name, xkb::STATE_MODS_EFFECTIVE);
+    Modifiers {
+        ctrl: active(xkb::MOD_NAME_CTRL),
+        alt: active(xkb::MOD_NAME_ALT),
+        shift: active(xkb::MOD_NAME_SHIFT),
+        logo: active(xkb::MOD_NAME_LOGO),
+        caps_lock: active(xkb::MOD_NAME_CAPS),
+        num_lock: active(xkb::MOD_NAME_NUM),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A keymap with only the A key, so no system keymaps are needed.
+    const KEYMAP: &str = r#"xkb_keymap {
+        xkb_keycodes { minimum = 8; maximum = 255; <AC01> = 38; };
+        xkb_types { type "ONE_LEVEL" { modifiers = none; level_name[Level1] = "Any"; }; };
+        xkb_compat { };
+        xkb_symbols { key <AC01> { [ a ] }; };
+    };"#;
+
+    /// The evdev code of the A key.
+    const KEY_A: u32 = 30;
+
+    fn keyboard() -> Keyboard {
+        let mut keyboard = Keyboard::new();
+        keyboard.set_keymap(KEYMAP.to_owned());
+        keyboard
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn release(key: u32) -> wl_keyboard::Event {
+        wl_keyboard::Event::Key {
+            serial: 8,
+            time: 2000,
+            key,
+            state: KeyState::Released,
+        }
+    }
+
+    #[test]
+    fn first_repeat_after_delay() {
+        let mut keyboard = keyboard();
+        let start = Instant::now();
+        keyboard.start_repeat(KEY_A, 7, 1000, start);
+        assert_eq!(keyboard.next_repeat(), Some(start + ms(600)));
+        assert!(keyboard.repeat(start + ms(599)).is_none());
+        match keyboard.repeat(start + ms(600)) {
+            Some(InputEvent::KeyDown { key, time, serial }) => {
+                assert_eq!(key.keysym, keysyms::KEY_a);
+                assert!(key.repeat);
+                assert_eq!(time, 1600);
+                assert_eq!(serial, 7);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn repeats_at_rate() {
+        let mut keyboard = keyboard();
+        let start = Instant::now();
+        keyboard.start_repeat(KEY_A, 7, 1000, start);
+        assert!(keyboard.repeat(start + ms(600)).is_some());
+        assert_eq!(keyboard.next_repeat(), Some(start + ms(640)));
+        assert!(keyboard.repeat(start + ms(639)).is_none());
+        assert!(keyboard.repeat(start + ms(640)).is_some());
+        assert_eq!(keyboard.next_repeat(), Some(start + ms(680)));
+    }
+
+    #[test]
+    fn repeat_info_from_compositor() {
+        let mut keyboard = keyboard();
+        keyboard.handle(wl_keyboard::Event::RepeatInfo { rate: 50, delay: 200 });
+        let start = Instant::now();
+        keyboard.start_repeat(KEY_A, 7, 1000, start);
+        assert_eq!(keyboard.next_repeat(), Some(start + ms(200)));
+        assert!(keyboard.repeat(start + ms(200)).is_some());
+        assert_eq!(keyboard.next_repeat(), Some(start + ms(220)));
+    }
+
+    #[test]
+    fn missed_repeats_are_skipped() {
+        let mut keyboard = keyboard();
+        let start = Instant::now();
+        keyboard.start_repeat(KEY_A, 7, 1000, start);
+        assert!(keyboard.repeat(start + ms(2000)).is_some());
+        assert!(keyboard.repeat(start + ms(2000)).is_none());
+        assert_eq!(keyboard.next_repeat(), Some(start + ms(2040)));
+    }
+
+    #[test]
+    fn release_stops_repeat() {
+        let mut keyboard = keyboard();
+        keyboard.start_repeat(KEY_A, 7, 1000, Instant::now());
+        // Releasing another key keeps the held one repeating.
+        keyboard.handle(release(KEY_A + 1));
+        assert!(keyboard.next_repeat().is_some());
+        keyboard.handle(release(KEY_A));
+        assert!(keyboard.next_repeat().is_none());
+    }
+
+    #[test]
+    fn zero_rate_disables_repeat() {
+        let mut keyboard = keyboard();
+        keyboard.start_repeat(KEY_A, 7, 1000, Instant::now());
+        keyboard.handle(wl_keyboard::Event::RepeatInfo { rate: 0, delay: 600 });
+        assert!(keyboard.next_repeat().is_none());
+        keyboard.start_repeat(KEY_A, 7, 1000, Instant::now());
+        assert!(keyboard.next_repeat().is_none());
+    }
+
+    #[test]
+    fn keys_without_keymap_do_not_repeat() {
+        let mut keyboard = Keyboard::new();
+        keyboard.start_repeat(KEY_A, 7, 1000, Instant::now());
+        assert!(keyboard.next_repeat().is_none());
+    }
+}
@@ -0,0 +1,110 @@
+//! Bean draws windows on Wayland with Pathfinder.
+//!
+//! Bean owns the connection to the compositor, the EGL context and the
+//! Pathfinder renderer. Applications implement [`Application`] to draw the
+//! window contents and react to input, and hand it to [`Window::run`].
+//!
+//! ```no_run
+//! use bean::{Application, Window};
+//! use pathfinder_canvas::CanvasRenderingContext2D;
+//!
+//! struct Empty;
+//!
+//! impl Application for Empty {
+//!     fn draw(&mut self, _canvas: &mut CanvasRenderingContext2D) {}
+//! }
+//!
+//! fn main() -> Result<(), bean::Error> {
+//!     Window::new("empty")?.run(Empty)
+//! }
+//! ```
+
+#[macro_use(event_enum)]
+extern crate wayland_client;
+
+pub use calloop;
+
+use pathfinder_canvas::CanvasRenderingContext2D;
+use pathfinder_geometry::vector::Vector2I;
+
+mod context;
+mod cursor;
+mod decorations;
+mod error;
+mod event;
+mod executor;
+mod headless;
+mod image;
+mod input;
+mod keyboard;
+mod output;
+mod pointer;
+mod protocols;
+mod proxy;
+mod render;
+mod shell;
+pub mod testing;
+mod touch;
+mod window;
+
+pub use crate::context::GlVersion;
+pub use crate::cursor::CursorShape;
+pub use crate::error::Error;
+pub use crate::event::{InputEvent, MouseButton, ScrollEvent, ScrollSource, TouchPoint};
+pub use crate::executor::{next_frame, sleep, spawn_local, NextFrame, Sleep};
+pub use crate::headless::Headless;
+pub use crate::image::Image;
+pub use crate::keyboard::{keysyms, KeyEvent, Keysym, Modifiers};
+pub use crate::output::Output;
+pub use crate::proxy::EventLoopProxy;
+pub use crate::shell::{ResizeEdge, WindowState};
+pub use crate::window::{Window, WindowHandle};
+
+/// The interface between bean and the code using it.
+///
+/// Only `draw` is required, the input and resize callbacks default to
+/// ignoring the event. Every callback gets a [`WindowHandle`] to request a
+/// redraw in response to the event.
+pub trait Application {
+    /// Draws the window contents.
+    ///
+    /// The canvas is cleared to white. Its origin is the top left corner of
+    /// the window contents, below the title bar if bean draws one.
+    fn draw(&mut self, canvas: &mut CanvasRenderingContext2D);
+
+    /// Called for every input event on the window.
+    ///
+    /// `seat` is the name of the seat the device belongs to, e.g. `seat0`.
+    /// Events are delivered in the order they happened, before the next
+    /// frame is drawn.
+    fn on_input(&mut self, _window: &WindowHandle, _seat: &str, _event: InputEvent) {}
+
+    /// Called after the compositor changed the size of the window.
+    ///
+    /// `size` is the size of the window contents, without decorations. A
+    /// redraw is already scheduled when this is called.
+    fn on_resize(&mut self, _window: &WindowHandle, _size: Vector2I) {}
+
+    /// Called after the compositor changed the state of the window, e.g.
+    /// when it was maximized or lost focus.
+    ///
+    /// A redraw is already scheduled when this is called.
+    fn on_state_change(&mut self, _window: &WindowHandle, _state: WindowState) {}
+
+    /// Called when the user asks to close the window.
+    ///
+    /// Closes the window by default. Applications can ask to save changes
+    /// first, and call [`WindowHandle::close`] later.
+    fn on_close_request(&mut self, window: &WindowHandle) {
+        window.close();
+    }
+
+    /// Called when the connection to the compositor was lost.
+    ///
+    /// This is the last chance to save state before [`Window::run`] returns
+    /// `error`. Returning true opens the window again on a new connection
+    /// instead, set `WAYLAND_DISPLAY` before to connect to another compositor.
+    fn on_disconnect(&mut self, _window: &WindowHandle, _error: &Error) -> bool {
+        false
+    }
+}
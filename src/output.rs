@@ -0,0 +1,234 @@
+//! The outputs of the compositor and their scale.
+//!
+//! The window is drawn with the highest scale of all outputs it is shown on,
+//! so it stays sharp on the output with the highest pixel density. If the
+//! compositor supports fractional scales, it picks the scale instead.
+
+use crate::protocols::fractional_scale_v1::client::{wp_fractional_scale_manager_v1, wp_fractional_scale_v1};
+use pathfinder_geometry::vector::Vector2I;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use wayland_client::protocol::{wl_output, wl_registry, wl_surface};
+use wayland_client::{Attached, GlobalEvent, GlobalManager, Main};
+use wayland_protocols::viewporter::client::{wp_viewport, wp_viewporter};
+
+/// Fractional scales are sent as multiples of 1/120.
+const SCALE_DENOMINATOR: f32 = 120.0;
+
+/// The highest `wl_output` version bean understands.
+///
+/// Version 2 adds the scale of the output.
+const OUTPUT_VERSION: u32 = 2;
+
+struct OutputInfo {
+    wl_output: Main<wl_output::WlOutput>,
+    make: String,
+    model: String,
+    // The scale sent with the last done event.
+    scale: i32,
+    // The scale sent since the last done event.
+    pending_scale: i32,
+}
+
+/// A monitor connected to the computer.
+///
+/// Used to pick the monitor a fullscreen window is shown on.
+#[derive(Clone)]
+pub struct Output {
+    pub(crate) wl_output: wl_output::WlOutput,
+    make: String,
+    model: String,
+}
+
+impl Output {
+    /// Returns the manufacturer of the monitor.
+    pub fn make(&self) -> &str {
+        &self.make
+    }
+
+    /// Returns the model name of the monitor.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    // Outputs by the id of their global.
+    outputs: HashMap<u32, OutputInfo>,
+    // The outputs the window surface is shown on.
+    entered: Vec<wl_output::WlOutput>,
+}
+
+/// All outputs of the compositor and the ones the window is shown on.
+///
+/// Handles are cheap to clone, they share the same outputs.
+#[derive(Clone, Default)]
+pub(crate) struct Outputs {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Outputs {
+    /// Binds new outputs and forgets removed ones.
+    ///
+    /// Called for every global announced by the registry.
+    pub(crate) fn handle_global(&self, event: &GlobalEvent, registry: &Attached<wl_registry::WlRegistry>) {
+        match *event {
+            GlobalEvent::New { id, ref interface, version } if interface == "wl_output" => {
+                let wl_output = registry.bind::<wl_output::WlOutput>(version.min(OUTPUT_VERSION), id);
+                let inner = Rc::downgrade(&self.inner);
+                wl_output.quick_assign(move |_, event, _| {
+                    let inner = match inner.upgrade() {
+                        Some(inner) => inner,
+                        None => return,
+                    };
+                    let mut inner = inner.borrow_mut();
+                    let output = match inner.outputs.get_mut(&id) {
+                        Some(output) => output,
+                        None => return,
+                    };
+                    // Changes are applied atomically with the done event.
+                    match event {
+                        wl_output::Event::Geometry { make, model, .. } => {
+                            output.make = make;
+                            output.model = model;
+                        }
+                        wl_output::Event::Scale { factor } => output.pending_scale = factor,
+                        wl_output::Event::Done => output.scale = output.pending_scale,
+                        _ => {}
+                    }
+                });
+                let output = OutputInfo {
+                    wl_output,
+                    make: String::new(),
+                    model: String::new(),
+                    scale: 1,
+                    pending_scale: 1,
+                };
+                self.inner.borrow_mut().outputs.insert(id, output);
+            }
+            GlobalEvent::Removed { id, ref interface } if interface == "wl_output" => {
+                let mut inner = self.inner.borrow_mut();
+                if let Some(output) = inner.outputs.remove(&id) {
+                    let wl_output: &wl_output::WlOutput = &output.wl_output;
+                    inner.entered.retain(|entered| entered != wl_output);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Forgets all outputs, e.g. after the connection was closed.
+    pub(crate) fn clear(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.outputs.clear();
+        inner.entered.clear();
+    }
+
+    /// Keeps track of the outputs `surface` is shown on.
+    pub(crate) fn track_surface(&self, surface: &Main<wl_surface::WlSurface>) {
+        let inner = Rc::downgrade(&self.inner);
+        surface.quick_assign(move |_, event, _| {
+            let inner = match inner.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            let mut inner = inner.borrow_mut();
+            match event {
+                wl_surface::Event::Enter { output } => inner.entered.push(output),
+                wl_surface::Event::Leave { output } => inner.entered.retain(|entered| *entered != output),
+                _ => {}
+            }
+        });
+    }
+
+    /// Returns all outputs of the compositor.
+    pub(crate) fn list(&self) -> Vec<Output> {
+        let inner = self.inner.borrow();
+        inner
+            .outputs
+            .values()
+            .map(|output| Output {
+                wl_output: (**output.wl_output).clone(),
+                make: output.make.clone(),
+                model: output.model.clone(),
+            })
+            .collect()
+    }
+
+    /// Returns the highest scale of the outputs the surface is shown on.
+    ///
+    /// Defaults to 1 as long as the surface is not shown anywhere.
+    pub(crate) fn scale(&self) -> i32 {
+        let inner = self.inner.borrow();
+        inner
+            .outputs
+            .values()
+            .filter(|output| inner.entered.iter().any(|entered| entered == &**output.wl_output))
+            .map(|output| output.scale)
+            .max()
+            .unwrap_or(1)
+            .max(1)
+    }
+}
+
+/// The fractional scale the compositor prefers for a surface.
+///
+/// Buffers of any size are scaled to the window size with a viewport, the
+/// buffer scale of the surface stays 1.
+pub(crate) struct FractionalScale {
+    fractional_scale: Main<wp_fractional_scale_v1::WpFractionalScaleV1>,
+    viewport: Main<wp_viewport::WpViewport>,
+    // The preferred scale in multiples of 1/120, once it is known.
+    preferred: Rc<Cell<Option<u32>>>,
+}
+
+impl FractionalScale {
+    /// Listens for the preferred scale of `surface`.
+    ///
+    /// Returns `None` if the compositor does not support fractional scales
+    /// or viewports.
+    pub(crate) fn new(globals: &GlobalManager, surface: &wl_surface::WlSurface) -> Option<FractionalScale> {
+        let manager = globals
+            .instantiate_exact::<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1>(1)
+            .ok()?;
+        let viewporter = globals.instantiate_exact::<wp_viewporter::WpViewporter>(1).ok()?;
+
+        let preferred = Rc::new(Cell::new(None));
+        let fractional_scale = manager.get_fractional_scale(surface);
+        let preferred_handle = preferred.clone();
+        fractional_scale.quick_assign(move |_, event, _| {
+            if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+                preferred_handle.set(Some(scale));
+            }
+        });
+        let viewport = viewporter.get_viewport(surface);
+        // The objects created from the globals stay valid without them.
+        manager.destroy();
+        viewporter.destroy();
+
+        Some(FractionalScale {
+            fractional_scale,
+            viewport,
+            preferred,
+        })
+    }
+
+    /// Returns the scale preferred by the compositor, if it sent one yet.
+    pub(crate) fn preferred(&self) -> Option<f32> {
+        self.preferred.get().map(|scale| scale as f32 / SCALE_DENOMINATOR)
+    }
+
+    /// Destroys the protocol objects, must be called before the surface is
+    /// destroyed.
+    pub(crate) fn destroy(&self) {
+        self.fractional_scale.destroy();
+        self.viewport.destroy();
+    }
+
+    /// Shows the buffer at `size` in surface coordinates.
+    pub(crate) fn set_size(&self, size: Vector2I) {
+        self.viewport.set_destination(size.x(), size.y());
+    }
+}
@@ -0,0 +1,253 @@
+//! Pointer input.
+
+use crate::cursor::CursorShape;
+use crate::event::{InputEvent, MouseButton, ScrollEvent, ScrollSource};
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use wayland_client::protocol::wl_pointer::{self, Axis, AxisSource};
+
+/// The first `wl_pointer` version with frame events.
+const FRAME_VERSION: u32 = 5;
+
+/// Keeps the state of a pointer between protocol events.
+///
+/// Since version 5 the compositor groups scroll events that belong together,
+/// e.g. diagonal touchpad scrolling, into frames. They are collected until the
+/// frame ends and reported as a single event.
+#[derive(Default)]
+pub(crate) struct Pointer {
+    scroll: Option<ScrollEvent>,
+    /// Serial of the enter event while the pointer is over the window.
+    pub(crate) enter_serial: Option<u32>,
+    /// The cursor shown since the pointer entered the window.
+    pub(crate) cursor: Option<CursorShape>,
+}
+
+impl Pointer {
+    /// Processes a pointer event and returns the input event it describes.
+    ///
+    /// `version` is the version of the `wl_pointer` that sent the event.
+    pub(crate) fn handle(&mut self, event: wl_pointer::Event, version: u32) -> Option<InputEvent> {
+        match event {
+            wl_pointer::Event::Enter {
+                serial,
+                surface_x,
+                surface_y,
+                ..
+            } => {
+                // The cursor has to be set again after every enter.
+                self.enter_serial = Some(serial);
+                self.cursor = None;
+                Some(InputEvent::PointerEnter {
+                    position: Vector2F::new(surface_x as f32, surface_y as f32),
+                    serial: Some(serial),
+                })
+            }
+            wl_pointer::Event::Leave { serial, .. } => {
+                self.enter_serial = None;
+                Some(InputEvent::PointerLeave { serial: Some(serial) })
+            }
+            wl_pointer::Event::Motion {
+                time,
+                surface_x,
+                surface_y,
+            } => Some(InputEvent::PointerMotion {
+                position: Vector2F::new(surface_x as f32, surface_y as f32),
+                time,
+            }),
+            wl_pointer::Event::Button {
+                serial,
+                time,
+                button,
+                state,
+            } => Some(InputEvent::PointerButton {
+                button: MouseButton::from(button),
+                pressed: state == wl_pointer::ButtonState::Pressed,
+                time,
+                serial,
+            }),
+            wl_pointer::Event::Axis { time, axis, value } => {
+                let scroll = self.scroll(time);
+                match axis {
+                    Axis::HorizontalScroll => scroll.delta = scroll.delta + Vector2F::new(value as f32, 0.0),
+                    Axis::VerticalScroll => scroll.delta = scroll.delta + Vector2F::new(0.0, value as f32),
+                    _ => {}
+                }
+                self.end_scroll_without_frame(version)
+            }
+            wl_pointer::Event::AxisSource { axis_source } => {
+                self.scroll(0).source = match axis_source {
+                    AxisSource::Wheel => ScrollSource::Wheel,
+                    AxisSource::Finger => ScrollSource::Finger,
+                    AxisSource::Continuous => ScrollSource::Continuous,
+                    AxisSource::WheelTilt => ScrollSource::WheelTilt,
+                    _ => ScrollSource::Unknown,
+                };
+                None
+            }
+            wl_pointer::Event::AxisDiscrete { axis, discrete } => {
+                // The protocol version of wayland-client predates axis_value120,
+                // discrete steps are always whole clicks.
+                let scroll = self.scroll(0);
+                match axis {
+                    Axis::HorizontalScroll => scroll.v120 = scroll.v120 + Vector2I::new(discrete * 120, 0),
+                    Axis::VerticalScroll => scroll.v120 = scroll.v120 + Vector2I::new(0, discrete * 120),
+                    _ => {}
+                }
+                None
+            }
+            wl_pointer::Event::AxisStop { time, axis } => {
+                let scroll = self.scroll(time);
+                match axis {
+                    Axis::HorizontalScroll => scroll.horizontal_stop = true,
+                    Axis::VerticalScroll => scroll.vertical_stop = true,
+                    _ => {}
+                }
+                None
+            }
+            wl_pointer::Event::Frame => self.scroll.take().map(InputEvent::PointerScroll),
+            _ => None,
+        }
+    }
+
+    /// Returns the scroll event of the current frame.
+    ///
+    /// `time` is used if the frame does not have a time yet.
+    fn scroll(&mut self, time: u32) -> &mut ScrollEvent {
+        let scroll = self.scroll.get_or_insert_with(|| ScrollEvent {
+            delta: Vector2F::default(),
+            v120: Vector2I::default(),
+            source: ScrollSource::Unknown,
+            horizontal_stop: false,
+            vertical_stop: false,
+            time,
+        });
+        if scroll.time == 0 {
+            scroll.time = time;
+        }
+        scroll
+    }
+
+    /// Old pointers send every axis event on its own.
+    fn end_scroll_without_frame(&mut self, version: u32) -> Option<InputEvent> {
+        if version >= FRAME_VERSION {
+            return None;
+        }
+        self.scroll.take().map(InputEvent::PointerScroll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scroll(event: Option<InputEvent>) -> ScrollEvent {
+        match event {
+            Some(InputEvent::PointerScroll(scroll)) => scroll,
+            event => panic!("expected a scroll event, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn frame_groups_scroll_events() {
+        let mut pointer = Pointer::default();
+        let events = vec![
+            wl_pointer::Event::AxisSource {
+                axis_source: AxisSource::Wheel,
+            },
+            wl_pointer::Event::AxisDiscrete {
+                axis: Axis::VerticalScroll,
+                discrete: 1,
+            },
+            wl_pointer::Event::Axis {
+                time: 10,
+                axis: Axis::VerticalScroll,
+                value: 15.0,
+            },
+            wl_pointer::Event::Axis {
+                time: 10,
+                axis: Axis::HorizontalScroll,
+                value: -5.0,
+            },
+        ];
+        for event in events {
+            assert_eq!(pointer.handle(event, FRAME_VERSION), None);
+        }
+        let scroll = scroll(pointer.handle(wl_pointer::Event::Frame, FRAME_VERSION));
+        assert_eq!(scroll.delta, Vector2F::new(-5.0, 15.0));
+        assert_eq!(scroll.v120, Vector2I::new(0, 120));
+        assert_eq!(scroll.source, ScrollSource::Wheel);
+        assert_eq!(scroll.time, 10);
+        assert!(!scroll.horizontal_stop && !scroll.vertical_stop);
+        // The frame is reported only once.
+        assert_eq!(pointer.handle(wl_pointer::Event::Frame, FRAME_VERSION), None);
+    }
+
+    #[test]
+    fn discrete_steps_in_v120() {
+        let mut pointer = Pointer::default();
+        pointer.handle(
+            wl_pointer::Event::AxisDiscrete {
+                axis: Axis::HorizontalScroll,
+                discrete: -2,
+            },
+            FRAME_VERSION,
+        );
+        pointer.handle(
+            wl_pointer::Event::AxisDiscrete {
+                axis: Axis::VerticalScroll,
+                discrete: 3,
+            },
+            FRAME_VERSION,
+        );
+        let scroll = scroll(pointer.handle(wl_pointer::Event::Frame, FRAME_VERSION));
+        assert_eq!(scroll.v120, Vector2I::new(-240, 360));
+    }
+
+    #[test]
+    fn axis_stop_flags() {
+        let mut pointer = Pointer::default();
+        pointer.handle(
+            wl_pointer::Event::AxisSource {
+                axis_source: AxisSource::Finger,
+            },
+            FRAME_VERSION,
+        );
+        pointer.handle(
+            wl_pointer::Event::AxisStop {
+                time: 20,
+                axis: Axis::VerticalScroll,
+            },
+            FRAME_VERSION,
+        );
+        let scroll = scroll(pointer.handle(wl_pointer::Event::Frame, FRAME_VERSION));
+        assert_eq!(scroll.source, ScrollSource::Finger);
+        assert!(scroll.vertical_stop);
+        assert!(!scroll.horizontal_stop);
+        assert_eq!(scroll.delta, Vector2F::default());
+        assert_eq!(scroll.time, 20);
+    }
+
+    #[test]
+    fn old_pointers_report_every_axis() {
+        let mut pointer = Pointer::default();
+        let vertical = scroll(pointer.handle(
+            wl_pointer::Event::Axis {
+                time: 10,
+                axis: Axis::VerticalScroll,
+                value: 15.0,
+            },
+            FRAME_VERSION - 1,
+        ));
+        assert_eq!(vertical.delta, Vector2F::new(0.0, 15.0));
+        let horizontal = scroll(pointer.handle(
+            wl_pointer::Event::Axis {
+                time: 11,
+                axis: Axis::HorizontalScroll,
+                value: 7.5,
+            },
+            FRAME_VERSION - 1,
+        ));
+        assert_eq!(horizontal.delta, Vector2F::new(7.5, 0.0));
+        assert_eq!(horizontal.time, 11);
+    }
+}
@@ -0,0 +1,40 @@
+//! Protocols that are not yet part of wayland-protocols.
+//!
+//! The code is generated by `build.rs` from the XML files in `protocols/`.
+
+/// Lets the compositor draw the cursor from a list of standard shapes.
+pub(crate) mod cursor_shape_v1 {
+    #![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+    #![allow(non_upper_case_globals, non_snake_case, unused_imports)]
+    #![allow(missing_docs, clippy::all)]
+
+    pub(crate) mod client {
+        pub(crate) use wayland_client::protocol::wl_pointer;
+        pub(crate) use wayland_client::sys;
+        pub(crate) use wayland_client::{AnonymousObject, Attached, Main, Proxy, ProxyMap};
+        pub(crate) use wayland_commons::map::{Object, ObjectMetadata};
+        pub(crate) use wayland_commons::smallvec;
+        pub(crate) use wayland_commons::wire::{Argument, ArgumentType, Message, MessageDesc};
+        pub(crate) use wayland_commons::{Interface, MessageGroup};
+        pub(crate) use wayland_protocols::unstable::tablet::v2::client::zwp_tablet_tool_v2;
+        include!(concat!(env!("OUT_DIR"), "/cursor-shape-v1_client_api.rs"));
+    }
+}
+
+/// Lets the compositor suggest a fractional scale for a surface.
+pub(crate) mod fractional_scale_v1 {
+    #![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+    #![allow(non_upper_case_globals, non_snake_case, unused_imports)]
+    #![allow(missing_docs, clippy::all)]
+
+    pub(crate) mod client {
+        pub(crate) use wayland_client::protocol::wl_surface;
+        pub(crate) use wayland_client::sys;
+        pub(crate) use wayland_client::{AnonymousObject, Attached, Main, Proxy, ProxyMap};
+        pub(crate) use wayland_commons::map::{Object, ObjectMetadata};
+        pub(crate) use wayland_commons::smallvec;
+        pub(crate) use wayland_commons::wire::{Argument, ArgumentType, Message, MessageDesc};
+        pub(crate) use wayland_commons::{Interface, MessageGroup};
+        include!(concat!(env!("OUT_DIR"), "/fractional-scale-v1_client_api.rs"));
+    }
+}
@@ -0,0 +1,35 @@
+//! Sending events to the window from other threads.
+
+use calloop::channel::Sender;
+
+/// Sends user events to the application from any thread.
+///
+/// Created with [`Window::create_proxy`](crate::Window::create_proxy). Every
+/// event wakes the event loop of the window and is passed to the callback
+/// given there, in the order the events were sent. Proxies can be cloned,
+/// all clones deliver to the same callback.
+pub struct EventLoopProxy<T> {
+    sender: Sender<T>,
+}
+
+impl<T> EventLoopProxy<T> {
+    pub(crate) fn new(sender: Sender<T>) -> EventLoopProxy<T> {
+        EventLoopProxy { sender }
+    }
+
+    /// Sends `event` to the application.
+    ///
+    /// Returns the event if the window was dropped and nobody is left to
+    /// receive it.
+    pub fn send(&self, event: T) -> Result<(), T> {
+        self.sender.send(event).map_err(|err| err.0)
+    }
+}
+
+impl<T> Clone for EventLoopProxy<T> {
+    fn clone(&self) -> EventLoopProxy<T> {
+        EventLoopProxy {
+            sender: self.sender.clone(),
+        }
+    }
+}
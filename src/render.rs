@@ -0,0 +1,58 @@
+//! Rendering canvases with Pathfinder.
+
+use crate::context::GlVersion;
+use crate::error::Error;
+use pathfinder_canvas::CanvasRenderingContext2D;
+use pathfinder_color::ColorF;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gl::GLDevice;
+use pathfinder_renderer::concurrent::executor::SequentialExecutor;
+use pathfinder_renderer::concurrent::scene_proxy::SceneProxy;
+use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererOptions};
+use pathfinder_renderer::gpu::renderer::Renderer;
+use pathfinder_renderer::options::BuildOptions;
+use pathfinder_resources::embedded::EmbeddedResourceLoader;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Creates the Pathfinder renderer for the current OpenGL context.
+///
+/// The shaders are compiled for `gl_version`, which must match the context.
+/// Every frame starts out filled with `background`.
+///
+/// Pathfinder panics if its shaders do not compile, the panic is turned
+/// into an error.
+pub(crate) fn create_renderer(
+    size: Vector2I,
+    gl_version: GlVersion,
+    background: ColorF,
+) -> Result<Renderer<GLDevice>, Error> {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        Renderer::new(
+            GLDevice::new(gl_version.pathfinder(), 0),
+            &EmbeddedResourceLoader::new(),
+            DestFramebuffer::full_window(size),
+            RendererOptions {
+                background_color: Some(background),
+            },
+        )
+    }))
+    .map_err(|payload| {
+        let msg = if let Some(msg) = payload.downcast_ref::<&str>() {
+            (*msg).to_owned()
+        } else if let Some(msg) = payload.downcast_ref::<String>() {
+            msg.clone()
+        } else {
+            "unknown error".to_owned()
+        };
+        Error::ShaderCompile(msg)
+    })
+}
+
+/// Turns the canvas into a scene and renders it with the given renderer.
+///
+/// The result is written to the destination framebuffer of the renderer,
+/// the caller is responsible for presenting it.
+pub(crate) fn render_canvas(canvas: CanvasRenderingContext2D, renderer: &mut Renderer<GLDevice>) {
+    let scene = SceneProxy::from_scene(canvas.into_canvas().into_scene(), SequentialExecutor);
+    scene.build_and_render(renderer, BuildOptions::default());
+}
@@ -0,0 +1,338 @@
+//! Turns a surface into a toplevel window.
+
+use crate::error::Error;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::Vector2I;
+use std::cell::Cell;
+use std::rc::Rc;
+use wayland_client::protocol::wl_output::WlOutput;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::protocol::{wl_shell, wl_shell_surface};
+use wayland_client::{GlobalManager, Main};
+use wayland_protocols::xdg_shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
+
+/// Smallest window size that can be requested by the compositor.
+pub(crate) const MIN_SIZE: (i32, i32) = (160, 120);
+
+/// An edge or corner of the window that is dragged to resize it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResizeEdge {
+    /// The top edge.
+    Top,
+    /// The bottom edge.
+    Bottom,
+    /// The left edge.
+    Left,
+    /// The right edge.
+    Right,
+    /// The top left corner.
+    TopLeft,
+    /// The top right corner.
+    TopRight,
+    /// The bottom left corner.
+    BottomLeft,
+    /// The bottom right corner.
+    BottomRight,
+}
+
+/// How the compositor currently shows the window.
+///
+/// Updated with every configure event of the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WindowState {
+    /// The window fills the screen, except for panels.
+    pub maximized: bool,
+    /// The window covers the whole screen.
+    pub fullscreen: bool,
+    /// The window is being resized by the user.
+    pub resizing: bool,
+    /// The window has focus and should look that way.
+    pub activated: bool,
+    /// The left edge touches another window or the screen edge.
+    pub tiled_left: bool,
+    /// The right edge touches another window or the screen edge.
+    pub tiled_right: bool,
+    /// The top edge touches another window or the screen edge.
+    pub tiled_top: bool,
+    /// The bottom edge touches another window or the screen edge.
+    pub tiled_bottom: bool,
+}
+
+impl WindowState {
+    /// Decodes the array of states sent with a toplevel configure event.
+    fn from_states(states: &[u8]) -> WindowState {
+        use wayland_protocols::xdg_shell::client::xdg_toplevel::State;
+        let mut window_state = WindowState::default();
+        for state in states.chunks_exact(4) {
+            let state = u32::from_ne_bytes([state[0], state[1], state[2], state[3]]);
+            match state {
+                _ if state == State::Maximized as u32 => window_state.maximized = true,
+                _ if state == State::Fullscreen as u32 => window_state.fullscreen = true,
+                _ if state == State::Resizing as u32 => window_state.resizing = true,
+                _ if state == State::Activated as u32 => window_state.activated = true,
+                _ if state == State::TiledLeft as u32 => window_state.tiled_left = true,
+                _ if state == State::TiledRight as u32 => window_state.tiled_right = true,
+                _ if state == State::TiledTop as u32 => window_state.tiled_top = true,
+                _ if state == State::TiledBottom as u32 => window_state.tiled_bottom = true,
+                // States from newer versions of the protocol.
+                _ => {}
+            }
+        }
+        window_state
+    }
+}
+
+/// State proposed by a toplevel configure event.
+#[derive(Clone, Copy)]
+struct Pending {
+    size: Option<Vector2I>,
+    state: WindowState,
+}
+
+/// Changes to the window requested by the application.
+pub(crate) enum Request {
+    Title(String),
+    AppId(String),
+    /// Shows the window fullscreen, on the given output if there is one.
+    Fullscreen(Option<WlOutput>),
+    UnsetFullscreen,
+    Maximized(bool),
+    Minimize,
+    /// A size of zero means no limit.
+    MinSize(Vector2I),
+    MaxSize(Vector2I),
+}
+
+/// The role object that turns our surface into a window.
+///
+/// `xdg_shell` is preferred, `wl_shell` is only used by compositors that
+/// do not implement it.
+pub(crate) enum ShellSurface {
+    Xdg {
+        wm_base: Main<xdg_wm_base::XdgWmBase>,
+        surface: Main<xdg_surface::XdgSurface>,
+        toplevel: Main<xdg_toplevel::XdgToplevel>,
+    },
+    Wl(Main<wl_shell_surface::WlShellSurface>),
+}
+
+impl ShellSurface {
+    /// Gives `surface` the toplevel role.
+    ///
+    /// Sizes suggested by the compositor are written to `window_size`,
+    /// the state of the window to `window_state`. `close_requested` is set
+    /// when the user asks to close the window.
+    pub(crate) fn new(
+        globals: &GlobalManager,
+        surface: &WlSurface,
+        title: &str,
+        window_size: Rc<Cell<Vector2I>>,
+        window_state: Rc<Cell<WindowState>>,
+        close_requested: Rc<Cell<bool>>,
+    ) -> Result<ShellSurface, Error> {
+        // The shell allows us to define our surface as a "toplevel", meaning the
+        // server will treat it as a window
+        //
+        // xdg_shell is the standard protocol for this, the deprecated wl_shell is
+        // only used if the compositor does not advertise xdg_wm_base.
+        //
+        // Version 2 reports tiled edges.
+        if let Ok(wm_base) = globals.instantiate_range::<xdg_wm_base::XdgWmBase>(1, 2) {
+            // This ping/pong mechanism is used by the wayland server to detect
+            // unresponsive applications
+            wm_base.quick_assign(|wm_base, event, _| {
+                if let xdg_wm_base::Event::Ping { serial } = event {
+                    wm_base.pong(serial);
+                }
+            });
+            // The toplevel configure event only proposes a size and state, they
+            // are applied once the whole configure sequence was received.
+            let pending_state = Rc::new(Cell::new(None));
+            let xdg_surface = wm_base.get_xdg_surface(surface);
+            // Every configure sequence is terminated by xdg_surface.configure,
+            // it has to be acknowledged before the next commit of the surface.
+            let pending = pending_state.clone();
+            xdg_surface.quick_assign(move |xdg_surface, event, _| {
+                if let xdg_surface::Event::Configure { serial } = event {
+                    if let Some(Pending { size, state }) = pending.take() {
+                        if let Some(new_size) = size {
+                            window_size.set(new_size);
+                        }
+                        window_state.set(state);
+                    }
+                    xdg_surface.ack_configure(serial);
+                }
+            });
+            let toplevel = xdg_surface.get_toplevel();
+            toplevel.quick_assign(move |_, event, _| match event {
+                xdg_toplevel::Event::Configure { width, height, states } => {
+                    // A size of zero means that we are free to pick the size.
+                    let size = if width > 0 && height > 0 {
+                        Some(clamp_size(Vector2I::new(width, height)))
+                    } else {
+                        None
+                    };
+                    let state = WindowState::from_states(&states);
+                    pending_state.set(Some(Pending { size, state }));
+                }
+                xdg_toplevel::Event::Close => close_requested.set(true),
+                _ => {}
+            });
+            toplevel.set_title(title.to_owned());
+            toplevel.set_min_size(MIN_SIZE.0, MIN_SIZE.1);
+            Ok(ShellSurface::Xdg {
+                wm_base,
+                surface: xdg_surface,
+                toplevel,
+            })
+        } else {
+            // Report the missing xdg_wm_base, nobody cares about wl_shell.
+            let shell = globals
+                .instantiate_exact::<wl_shell::WlShell>(1)
+                .map_err(|_| Error::missing_global(globals, "xdg_wm_base", 1))?;
+            let shell_surface = shell.get_shell_surface(surface);
+            shell_surface.quick_assign(move |shell_surface, event, _| {
+                use wayland_client::protocol::wl_shell_surface::Event;
+                match event {
+                    // This ping/pong mechanism is used by the wayland server to detect
+                    // unresponsive applications
+                    Event::Ping { serial } => shell_surface.pong(serial),
+                    // wl_shell has no acknowledgement, the size is a mere hint
+                    Event::Configure { width, height, .. } if width > 0 && height > 0 => {
+                        window_size.set(clamp_size(Vector2I::new(width, height)));
+                    }
+                    _ => {}
+                }
+            });
+            // Set our surface as toplevel and define its contents
+            shell_surface.set_toplevel();
+            shell_surface.set_title(title.to_owned());
+            Ok(ShellSurface::Wl(shell_surface))
+        }
+    }
+
+    /// Returns true if the surface must be configured before drawing to it.
+    pub(crate) fn needs_configure(&self) -> bool {
+        match self {
+            ShellSurface::Xdg { .. } => true,
+            ShellSurface::Wl(_) => false,
+        }
+    }
+
+    /// Sets the part of the surface that is the actual window.
+    ///
+    /// Everything outside, like a shadow, is ignored when the window is
+    /// placed or snapped to other windows.
+    pub(crate) fn set_window_geometry(&self, geometry: RectI) {
+        if let ShellSurface::Xdg { surface, .. } = self {
+            surface.set_window_geometry(geometry.min_x(), geometry.min_y(), geometry.width(), geometry.height());
+        }
+    }
+
+    /// Lets the user move the window with the pointer.
+    ///
+    /// `serial` is the serial of the button press that starts the move.
+    pub(crate) fn start_move(&self, seat: &WlSeat, serial: u32) {
+        match self {
+            ShellSurface::Xdg { toplevel, .. } => toplevel._move(seat, serial),
+            ShellSurface::Wl(shell_surface) => shell_surface._move(seat, serial),
+        }
+    }
+
+    /// Lets the user resize the window by dragging `edge` with the pointer.
+    pub(crate) fn start_resize(&self, seat: &WlSeat, serial: u32, edge: ResizeEdge) {
+        match self {
+            ShellSurface::Xdg { toplevel, .. } => {
+                use wayland_protocols::xdg_shell::client::xdg_toplevel::ResizeEdge as Edge;
+                let edge = match edge {
+                    ResizeEdge::Top => Edge::Top,
+                    ResizeEdge::Bottom => Edge::Bottom,
+                    ResizeEdge::Left => Edge::Left,
+                    ResizeEdge::Right => Edge::Right,
+                    ResizeEdge::TopLeft => Edge::TopLeft,
+                    ResizeEdge::TopRight => Edge::TopRight,
+                    ResizeEdge::BottomLeft => Edge::BottomLeft,
+                    ResizeEdge::BottomRight => Edge::BottomRight,
+                };
+                toplevel.resize(seat, serial, edge);
+            }
+            ShellSurface::Wl(shell_surface) => {
+                use wayland_client::protocol::wl_shell_surface::Resize;
+                let edge = match edge {
+                    ResizeEdge::Top => Resize::Top,
+                    ResizeEdge::Bottom => Resize::Bottom,
+                    ResizeEdge::Left => Resize::Left,
+                    ResizeEdge::Right => Resize::Right,
+                    ResizeEdge::TopLeft => Resize::TopLeft,
+                    ResizeEdge::TopRight => Resize::TopRight,
+                    ResizeEdge::BottomLeft => Resize::BottomLeft,
+                    ResizeEdge::BottomRight => Resize::BottomRight,
+                };
+                shell_surface.resize(seat, serial, edge);
+            }
+        }
+    }
+
+    /// Passes a request of the application on to the compositor.
+    ///
+    /// wl_shell cannot minimize windows or limit their size, these requests
+    /// are ignored there.
+    pub(crate) fn request(&self, request: Request) {
+        match self {
+            ShellSurface::Xdg { toplevel, .. } => match request {
+                Request::Title(title) => toplevel.set_title(title),
+                Request::AppId(app_id) => toplevel.set_app_id(app_id),
+                Request::Fullscreen(output) => toplevel.set_fullscreen(output.as_ref()),
+                Request::UnsetFullscreen => toplevel.unset_fullscreen(),
+                Request::Maximized(true) => toplevel.set_maximized(),
+                Request::Maximized(false) => toplevel.unset_maximized(),
+                Request::Minimize => toplevel.set_minimized(),
+                Request::MinSize(size) => toplevel.set_min_size(size.x(), size.y()),
+                Request::MaxSize(size) => toplevel.set_max_size(size.x(), size.y()),
+            },
+            ShellSurface::Wl(shell_surface) => match request {
+                Request::Title(title) => shell_surface.set_title(title),
+                Request::AppId(app_id) => shell_surface.set_class(app_id),
+                Request::Fullscreen(output) => {
+                    shell_surface.set_fullscreen(wl_shell_surface::FullscreenMethod::Default, 0, output.as_ref())
+                }
+                Request::Maximized(true) => shell_surface.set_maximized(None),
+                Request::UnsetFullscreen | Request::Maximized(false) => shell_surface.set_toplevel(),
+                Request::Minimize | Request::MinSize(_) | Request::MaxSize(_) => {}
+            },
+        }
+    }
+
+    /// Destroys the role objects.
+    ///
+    /// The surface must not be used as a window afterwards.
+    pub(crate) fn destroy(&self) {
+        match self {
+            ShellSurface::Xdg {
+                wm_base,
+                surface,
+                toplevel,
+            } => {
+                toplevel.destroy();
+                surface.destroy();
+                wm_base.destroy();
+            }
+            // wl_shell_surface is destroyed together with the surface.
+            ShellSurface::Wl(_) => {}
+        }
+    }
+
+    /// Returns the xdg_toplevel of the window, if xdg_shell is used.
+    pub(crate) fn toplevel(&self) -> Option<&Main<xdg_toplevel::XdgToplevel>> {
+        match self {
+            ShellSurface::Xdg { toplevel, .. } => Some(toplevel),
+            ShellSurface::Wl(_) => None,
+        }
+    }
+}
+
+/// Makes sure the window never gets smaller than `MIN_SIZE`.
+fn clamp_size(size: Vector2I) -> Vector2I {
+    Vector2I::new(size.x().max(MIN_SIZE.0), size.y().max(MIN_SIZE.1))
+}
@@ -0,0 +1,286 @@
+//! Golden image tests for drawing code.
+//!
+//! Drawings are rendered with [`Headless`] and compared to reference PNG
+//! files. If they differ, the actual image, the expected image and an image
+//! highlighting the differences are written next to the reference:
+//! `house.png` becomes `house.actual.png`, `house.expected.png` and
+//! `house.diff.png`.
+//!
+//! Set `BEAN_BLESS=1` to replace the reference images with the rendered
+//! ones instead of comparing them.
+//!
+//! ```no_run
+//! use bean::testing::{assert_golden, Tolerance};
+//! use pathfinder_geometry::rect::RectF;
+//! use pathfinder_geometry::vector::{Vector2F, Vector2I};
+//!
+//! assert_golden("tests/golden/rect.png", Vector2I::new(64, 64), Tolerance::default(), |canvas| {
+//!     canvas.fill_rect(RectF::new(Vector2F::new(8.0, 8.0), Vector2F::new(48.0, 48.0)));
+//! });
+//! ```
+//!
+//! [`Headless`]: crate::Headless
+
+use crate::headless::Headless;
+use crate::image::Image;
+use pathfinder_canvas::CanvasRenderingContext2D;
+use pathfinder_geometry::vector::Vector2I;
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// How much a rendered image may deviate from its reference.
+///
+/// Rasterizers differ slightly in how they antialias edges, so an exact
+/// comparison is usually too strict.
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// The largest difference of a single color channel that still counts
+    /// as equal.
+    pub channel: u8,
+    /// The number of pixels that may exceed the channel tolerance.
+    pub max_differing_pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Tolerance {
+        Tolerance {
+            channel: 2,
+            max_differing_pixels: 0,
+        }
+    }
+}
+
+/// The result of comparing two images that do not match.
+#[derive(Clone, Debug)]
+pub enum Mismatch {
+    /// The images have different dimensions.
+    Size {
+        /// Width and height of the rendered image.
+        actual: (u32, u32),
+        /// Width and height of the reference image.
+        expected: (u32, u32),
+    },
+    /// Too many pixels differ.
+    Pixels {
+        /// The number of pixels exceeding the channel tolerance.
+        differing_pixels: usize,
+        /// The largest channel difference seen in any pixel.
+        max_channel_difference: u8,
+        /// The expected image faded to gray with differing pixels in red.
+        diff: Image,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::Size { actual, expected } => write!(
+                f,
+                "image is {}x{} but the reference is {}x{}",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            Mismatch::Pixels {
+                differing_pixels,
+                max_channel_difference,
+                ..
+            } => write!(
+                f,
+                "{} pixels differ, the largest channel difference is {}",
+                differing_pixels, max_channel_difference
+            ),
+        }
+    }
+}
+
+/// Compares two images pixel by pixel.
+pub fn compare(actual: &Image, expected: &Image, tolerance: Tolerance) -> Result<(), Mismatch> {
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        return Err(Mismatch::Size {
+            actual: (actual.width, actual.height),
+            expected: (expected.width, expected.height),
+        });
+    }
+
+    let mut differing_pixels = 0;
+    let mut max_channel_difference = 0;
+    let mut diff = Vec::with_capacity(expected.data.len());
+    for (a, e) in actual.data.chunks(4).zip(expected.data.chunks(4)) {
+        let difference = a
+            .iter()
+            .zip(e)
+            .map(|(a, e)| (i16::from(*a) - i16::from(*e)).abs() as u8)
+            .max()
+            .unwrap_or(0);
+        max_channel_difference = max_channel_difference.max(difference);
+        if difference > tolerance.channel {
+            differing_pixels += 1;
+            diff.extend_from_slice(&[0xff, 0, 0, 0xff]);
+        } else {
+            // Fade the expected pixel, so the differences stand out.
+            let luma = (u32::from(e[0]) * 3 + u32::from(e[1]) * 6 + u32::from(e[2])) / 10;
+            let faded = (0xc0 + luma / 4) as u8;
+            diff.extend_from_slice(&[faded, faded, faded, 0xff]);
+        }
+    }
+
+    if differing_pixels > tolerance.max_differing_pixels {
+        return Err(Mismatch::Pixels {
+            differing_pixels,
+            max_channel_difference,
+            diff: Image {
+                width: expected.width,
+                height: expected.height,
+                data: diff,
+            },
+        });
+    }
+    Ok(())
+}
+
+/// Renders `draw` and panics if the result does not match the reference image.
+///
+/// A missing reference is an error too, the rendered image is written to
+/// the `.actual.png` path for review.
+pub fn assert_golden<P, F>(reference: P, size: Vector2I, tolerance: Tolerance, draw: F)
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut CanvasRenderingContext2D),
+{
+    let reference = reference.as_ref();
+    let mut headless = Headless::new(size).unwrap_or_else(|err| panic!("{}", err));
+    let actual = headless.render(draw).unwrap_or_else(|err| panic!("{}", err));
+
+    if env::var_os("BEAN_BLESS").map_or(false, |bless| bless != "0") {
+        save(&actual, reference);
+        return;
+    }
+
+    let expected = match Image::load_png(reference) {
+        Ok(expected) => expected,
+        Err(err) => {
+            save(&actual, &sibling(reference, "actual"));
+            panic!(
+                "could not read reference image {}: {}\nrun with BEAN_BLESS=1 to create it",
+                reference.display(),
+                err
+            );
+        }
+    };
+
+    if let Err(mismatch) = compare(&actual, &expected, tolerance) {
+        save(&actual, &sibling(reference, "actual"));
+        save(&expected, &sibling(reference, "expected"));
+        if let Mismatch::Pixels { diff, .. } = &mismatch {
+            save(diff, &sibling(reference, "diff"));
+        }
+        panic!("{} does not match: {}", reference.display(), mismatch);
+    }
+}
+
+/// Returns `dir/name.suffix.png` for `dir/name.png`.
+fn sibling(reference: &Path, suffix: &str) -> PathBuf {
+    let stem = reference.file_stem().unwrap_or_default().to_string_lossy();
+    reference.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+fn save(image: &Image, path: &Path) {
+    image
+        .save_png(path)
+        .unwrap_or_else(|err| panic!("could not write {}: {}", path.display(), err));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Image {
+        Image {
+            width,
+            height,
+            data: rgba.iter().copied().cycle().take(width as usize * height as usize * 4).collect(),
+        }
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let image = solid(4, 3, [10, 20, 30, 255]);
+        assert!(compare(&image, &image.clone(), Tolerance::default()).is_ok());
+    }
+
+    #[test]
+    fn small_channel_differences_match() {
+        let expected = solid(4, 3, [10, 20, 30, 255]);
+        let actual = solid(4, 3, [12, 18, 30, 255]);
+        assert!(compare(&actual, &expected, Tolerance::default()).is_ok());
+    }
+
+    #[test]
+    fn differing_pixels_within_budget_match() {
+        let expected = solid(4, 3, [0, 0, 0, 255]);
+        let mut actual = expected.clone();
+        actual.data[0] = 0xff;
+        let tolerance = Tolerance {
+            channel: 2,
+            max_differing_pixels: 1,
+        };
+        assert!(compare(&actual, &expected, tolerance).is_ok());
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let expected = solid(4, 3, [0, 0, 0, 255]);
+        let actual = solid(3, 4, [0, 0, 0, 255]);
+        match compare(&actual, &expected, Tolerance::default()) {
+            Err(Mismatch::Size { actual, expected }) => {
+                assert_eq!(actual, (3, 4));
+                assert_eq!(expected, (4, 3));
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn pixel_budget_exceeded() {
+        let expected = solid(4, 3, [0, 0, 0, 255]);
+        let mut actual = expected.clone();
+        actual.data[0] = 3;
+        actual.data[5] = 100;
+        match compare(&actual, &expected, Tolerance::default()) {
+            Err(Mismatch::Pixels {
+                differing_pixels,
+                max_channel_difference,
+                ..
+            }) => {
+                assert_eq!(differing_pixels, 2);
+                assert_eq!(max_channel_difference, 100);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn diff_marks_differing_pixels_red() {
+        let mut expected = solid(2, 1, [0xff, 0xff, 0xff, 0xff]);
+        expected.data[4..8].copy_from_slice(&[0, 0, 0, 0xff]);
+        let mut actual = expected.clone();
+        actual.data[0] = 0;
+        let diff = match compare(&actual, &expected, Tolerance::default()) {
+            Err(Mismatch::Pixels { diff, .. }) => diff,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert_eq!((diff.width, diff.height), (2, 1));
+        assert_eq!(diff.pixel(0, 0), [0xff, 0, 0, 0xff]);
+        // Matching black is faded to light gray.
+        assert_eq!(diff.pixel(1, 0), [0xc0, 0xc0, 0xc0, 0xff]);
+    }
+
+    #[test]
+    fn sibling_paths() {
+        assert_eq!(
+            sibling(Path::new("tests/golden/house.png"), "actual"),
+            PathBuf::from("tests/golden/house.actual.png")
+        );
+        assert_eq!(sibling(Path::new("house.png"), "diff"), PathBuf::from("house.diff.png"));
+    }
+}
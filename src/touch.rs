@@ -0,0 +1,282 @@
+//! Touchscreen input.
+
+use crate::event::{InputEvent, MouseButton, TouchPoint};
+use pathfinder_geometry::vector::Vector2F;
+use std::collections::HashMap;
+use wayland_client::protocol::wl_touch;
+
+/// Keeps the touch points of a touchscreen.
+///
+/// The compositor groups the events of all touch points that changed at the
+/// same time into a frame, shape and orientation are only known at the end
+/// of it. Events are therefore held back until the frame is complete.
+#[derive(Default)]
+pub(crate) struct Touch {
+    points: HashMap<i32, TouchPoint>,
+    pending: Vec<InputEvent>,
+    /// The touch point that drives the emulated pointer.
+    primary: Option<i32>,
+    /// Also report the primary touch point as pointer events.
+    pub(crate) emulate_pointer: bool,
+}
+
+impl Touch {
+    /// Processes a touch event and returns the input events of a completed frame.
+    pub(crate) fn handle(&mut self, event: wl_touch::Event) -> Vec<InputEvent> {
+        match event {
+            wl_touch::Event::Down {
+                serial,
+                time,
+                id,
+                x,
+                y,
+                ..
+            } => self.down(serial, time, id, Vector2F::new(x as f32, y as f32)),
+            wl_touch::Event::Motion { time, id, x, y } => {
+                if let Some(point) = self.points.get_mut(&id) {
+                    point.position = Vector2F::new(x as f32, y as f32);
+                    let point = *point;
+                    self.pending.push(InputEvent::TouchMotion { point, time });
+                    if self.primary == Some(id) {
+                        self.pending.push(InputEvent::PointerMotion {
+                            position: point.position,
+                            time,
+                        });
+                    }
+                }
+            }
+            wl_touch::Event::Up { serial, time, id } => {
+                self.points.remove(&id);
+                self.pending.push(InputEvent::TouchUp { id, time, serial });
+                if self.primary == Some(id) {
+                    self.primary = None;
+                    self.pending.push(InputEvent::PointerButton {
+                        button: MouseButton::Left,
+                        pressed: false,
+                        time,
+                        serial,
+                    });
+                }
+            }
+            wl_touch::Event::Shape { id, major, minor } => {
+                self.update(id, |point| point.shape = Some(Vector2F::new(major as f32, minor as f32)));
+            }
+            wl_touch::Event::Orientation { id, orientation } => {
+                self.update(id, |point| point.orientation = Some(orientation as f32));
+            }
+            wl_touch::Event::Frame => return self.pending.drain(..).collect(),
+            wl_touch::Event::Cancel => {
+                // The events of the current frame are void as well.
+                self.points.clear();
+                self.pending.clear();
+                let mut events = vec![InputEvent::TouchCancel];
+                // There is no protocol event behind the emulated leave, so
+                // there is no serial either.
+                if self.primary.take().is_some() {
+                    events.push(InputEvent::PointerLeave { serial: None });
+                }
+                return events;
+            }
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    /// Adds a touch point to the current frame.
+    fn down(&mut self, serial: u32, time: u32, id: i32, position: Vector2F) {
+        let point = TouchPoint {
+            id,
+            position,
+            shape: None,
+            orientation: None,
+        };
+        self.points.insert(id, point);
+        self.pending.push(InputEvent::TouchDown { point, time, serial });
+        if self.emulate_pointer && self.primary.is_none() {
+            self.primary = Some(id);
+            self.pending.push(InputEvent::PointerMotion { position, time });
+            self.pending.push(InputEvent::PointerButton {
+                button: MouseButton::Left,
+                pressed: true,
+                time,
+                serial,
+            });
+        }
+    }
+
+    /// Changes a touch point and the events of the current frame describing it.
+    fn update<F: Fn(&mut TouchPoint)>(&mut self, id: i32, f: F) {
+        if let Some(point) = self.points.get_mut(&id) {
+            f(point);
+        }
+        for event in &mut self.pending {
+            match event {
+                InputEvent::TouchDown { point, .. } | InputEvent::TouchMotion { point, .. } if point.id == id => {
+                    f(point)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(id: i32, x: f32, y: f32) -> TouchPoint {
+        TouchPoint {
+            id,
+            position: Vector2F::new(x, y),
+            shape: None,
+            orientation: None,
+        }
+    }
+
+    fn motion(id: i32, x: f64, y: f64) -> wl_touch::Event {
+        wl_touch::Event::Motion { time: 2, id, x, y }
+    }
+
+    #[test]
+    fn events_wait_for_the_frame() {
+        let mut touch = Touch::default();
+        touch.down(1, 1, 0, Vector2F::new(10.0, 20.0));
+        assert!(touch.handle(motion(0, 15.0, 25.0)).is_empty());
+        assert_eq!(
+            touch.handle(wl_touch::Event::Frame),
+            vec![
+                InputEvent::TouchDown {
+                    point: point(0, 10.0, 20.0),
+                    time: 1,
+                    serial: 1,
+                },
+                InputEvent::TouchMotion {
+                    point: point(0, 15.0, 25.0),
+                    time: 2,
+                },
+            ]
+        );
+        assert!(touch.handle(wl_touch::Event::Frame).is_empty());
+    }
+
+    #[test]
+    fn shape_and_orientation_patch_the_frame() {
+        let mut touch = Touch::default();
+        touch.down(1, 1, 0, Vector2F::new(10.0, 20.0));
+        touch.handle(motion(0, 15.0, 25.0));
+        touch.handle(wl_touch::Event::Shape {
+            id: 0,
+            major: 8.0,
+            minor: 4.0,
+        });
+        touch.handle(wl_touch::Event::Orientation { id: 0, orientation: 30.0 });
+        let mut expected = point(0, 10.0, 20.0);
+        expected.shape = Some(Vector2F::new(8.0, 4.0));
+        expected.orientation = Some(30.0);
+        let events = touch.handle(wl_touch::Event::Frame);
+        assert_eq!(
+            events[0],
+            InputEvent::TouchDown {
+                point: expected,
+                time: 1,
+                serial: 1,
+            }
+        );
+        expected.position = Vector2F::new(15.0, 25.0);
+        assert_eq!(events[1], InputEvent::TouchMotion { point: expected, time: 2 });
+
+        // Later frames keep the shape until the compositor changes it.
+        touch.handle(motion(0, 16.0, 26.0));
+        expected.position = Vector2F::new(16.0, 26.0);
+        assert_eq!(
+            touch.handle(wl_touch::Event::Frame),
+            vec![InputEvent::TouchMotion { point: expected, time: 2 }]
+        );
+    }
+
+    #[test]
+    fn primary_point_emulates_pointer() {
+        let mut touch = Touch::default();
+        touch.emulate_pointer = true;
+        touch.down(1, 1, 0, Vector2F::new(10.0, 20.0));
+        assert_eq!(
+            touch.handle(wl_touch::Event::Frame),
+            vec![
+                InputEvent::TouchDown {
+                    point: point(0, 10.0, 20.0),
+                    time: 1,
+                    serial: 1,
+                },
+                InputEvent::PointerMotion {
+                    position: Vector2F::new(10.0, 20.0),
+                    time: 1,
+                },
+                InputEvent::PointerButton {
+                    button: MouseButton::Left,
+                    pressed: true,
+                    time: 1,
+                    serial: 1,
+                },
+            ]
+        );
+
+        // A second finger does not move the pointer.
+        touch.down(2, 2, 1, Vector2F::new(50.0, 50.0));
+        touch.handle(motion(1, 55.0, 55.0));
+        let events = touch.handle(wl_touch::Event::Frame);
+        assert!(events.iter().all(|event| match event {
+            InputEvent::TouchDown { .. } | InputEvent::TouchMotion { .. } => true,
+            _ => false,
+        }));
+
+        touch.handle(motion(0, 12.0, 22.0));
+        touch.handle(wl_touch::Event::Up { serial: 3, time: 3, id: 0 });
+        assert_eq!(
+            touch.handle(wl_touch::Event::Frame),
+            vec![
+                InputEvent::TouchMotion {
+                    point: point(0, 12.0, 22.0),
+                    time: 2,
+                },
+                InputEvent::PointerMotion {
+                    position: Vector2F::new(12.0, 22.0),
+                    time: 2,
+                },
+                InputEvent::TouchUp {
+                    id: 0,
+                    time: 3,
+                    serial: 3,
+                },
+                InputEvent::PointerButton {
+                    button: MouseButton::Left,
+                    pressed: false,
+                    time: 3,
+                    serial: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cancel_discards_the_frame() {
+        let mut touch = Touch::default();
+        touch.down(1, 1, 0, Vector2F::new(10.0, 20.0));
+        assert_eq!(touch.handle(wl_touch::Event::Cancel), vec![InputEvent::TouchCancel]);
+        assert!(touch.handle(wl_touch::Event::Frame).is_empty());
+        // The canceled point is gone.
+        assert!(touch.handle(motion(0, 15.0, 25.0)).is_empty());
+        assert!(touch.handle(wl_touch::Event::Frame).is_empty());
+    }
+
+    #[test]
+    fn cancel_ends_the_emulated_pointer() {
+        let mut touch = Touch::default();
+        touch.emulate_pointer = true;
+        touch.down(1, 1, 0, Vector2F::new(10.0, 20.0));
+        touch.handle(wl_touch::Event::Frame);
+        assert_eq!(
+            touch.handle(wl_touch::Event::Cancel),
+            vec![InputEvent::TouchCancel, InputEvent::PointerLeave { serial: None }]
+        );
+    }
+}
@@ -0,0 +1,793 @@
+//! The window and its event loop.
+
+use crate::context::{Context, GlVersion};
+use crate::cursor::CursorShape;
+use crate::decorations::{Action, Decorations};
+use crate::error::Error;
+use crate::executor;
+use crate::input::Seats;
+use crate::output::{FractionalScale, Output, Outputs};
+use crate::protocols::cursor_shape_v1::client::wp_cursor_shape_manager_v1::WpCursorShapeManagerV1;
+use crate::proxy::EventLoopProxy;
+use crate::shell::{Request, ResizeEdge, ShellSurface, WindowState, MIN_SIZE};
+use crate::Application;
+use crate::render;
+use calloop::channel::{self, Channel};
+use calloop::generic::{Fd, Generic};
+use calloop::ping::PingSource;
+use calloop::{EventLoop, Interest, LoopHandle, Mode, Source};
+use pathfinder_canvas::{CanvasFontContext, CanvasRenderingContext2D, FillRule, Path2D};
+use pathfinder_color::ColorF;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use pathfinder_gl::GLDevice;
+use pathfinder_renderer::gpu::options::DestFramebuffer;
+use pathfinder_renderer::gpu::renderer::Renderer;
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::mem::{self, ManuallyDrop};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use wayland_client::protocol::{wl_callback, wl_compositor, wl_shm, wl_surface};
+use wayland_client::{Display, EventQueue, GlobalManager, Main};
+
+/// Window size used until the compositor suggests a different one.
+const DEFAULT_SIZE: (i32, i32) = (320, 240);
+
+/// Schedules redraws of the window and changes how it is shown.
+///
+/// Handles are cheap to clone and can be moved into event handlers. A new
+/// frame is only drawn if a redraw was requested or an animation is running,
+/// and never before the compositor signalled that the previous frame was
+/// presented.
+///
+/// Changes to the window are sent to the compositor before the next frame.
+/// The compositor may ignore them, [`WindowHandle::state`] tells what it
+/// actually did.
+#[derive(Clone, Default)]
+pub struct WindowHandle {
+    dirty: Rc<Cell<bool>>,
+    animating: Rc<Cell<bool>>,
+    frame_pending: Rc<Cell<bool>>,
+    cursor: Rc<Cell<CursorShape>>,
+    state: Rc<Cell<WindowState>>,
+    requests: Rc<RefCell<Vec<Request>>>,
+    // Moves and resizes started by the application.
+    actions: Rc<RefCell<Vec<Action>>>,
+    outputs: Outputs,
+    // Set when the user asks to close the window.
+    close_requested: Rc<Cell<bool>>,
+    closed: Rc<Cell<bool>>,
+}
+
+impl WindowHandle {
+    /// Marks the window content as outdated, it is redrawn for the next frame.
+    pub fn request_redraw(&self) {
+        self.dirty.set(true);
+    }
+
+    /// Keeps redrawing the window every frame while `animating` is set.
+    pub fn set_animating(&self, animating: bool) {
+        self.animating.set(animating);
+    }
+
+    /// Changes the cursor shown while the pointer is over the window.
+    ///
+    /// To use different cursors for parts of the window, set the cursor
+    /// whenever the pointer moves into another part.
+    pub fn set_cursor(&self, shape: CursorShape) {
+        self.cursor.set(shape);
+    }
+
+    /// Closes the window, [`Window::run`] returns before the next frame.
+    pub fn close(&self) {
+        self.closed.set(true);
+    }
+
+    /// Returns how the compositor shows the window.
+    pub fn state(&self) -> WindowState {
+        self.state.get()
+    }
+
+    /// Returns all outputs, e.g. to show the window fullscreen on one of them.
+    pub fn outputs(&self) -> Vec<Output> {
+        self.outputs.list()
+    }
+
+    /// Changes the title of the window.
+    pub fn set_title(&self, title: &str) {
+        self.request(Request::Title(title.to_owned()));
+    }
+
+    /// Sets the application id, used by the desktop to group windows and
+    /// find the application's `.desktop` file.
+    pub fn set_app_id(&self, app_id: &str) {
+        self.request(Request::AppId(app_id.to_owned()));
+    }
+
+    /// Shows the window fullscreen on an output picked by the compositor,
+    /// or restores it.
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        self.request(if fullscreen {
+            Request::Fullscreen(None)
+        } else {
+            Request::UnsetFullscreen
+        });
+    }
+
+    /// Shows the window fullscreen on `output`.
+    pub fn set_fullscreen_on(&self, output: &Output) {
+        self.request(Request::Fullscreen(Some(output.wl_output.clone())));
+    }
+
+    /// Maximizes the window or restores its size.
+    pub fn set_maximized(&self, maximized: bool) {
+        self.request(Request::Maximized(maximized));
+    }
+
+    /// Minimizes the window.
+    ///
+    /// The window cannot tell whether it is minimized, and it cannot bring
+    /// itself back.
+    pub fn set_minimized(&self) {
+        self.request(Request::Minimize);
+    }
+
+    /// Limits how small the user can make the window contents.
+    ///
+    /// `None` restores the default of 160×120 for the whole window.
+    pub fn set_min_size(&self, size: Option<Vector2I>) {
+        self.request(Request::MinSize(size.unwrap_or_default()));
+    }
+
+    /// Limits how large the user can make the window contents.
+    ///
+    /// `None` removes the limit.
+    pub fn set_max_size(&self, size: Option<Vector2I>) {
+        self.request(Request::MaxSize(size.unwrap_or_default()));
+    }
+
+    /// Lets the user move the window, e.g. by dragging a title bar drawn by
+    /// the application.
+    ///
+    /// `seat` and `serial` are those of the button press or touch that
+    /// starts the move. The compositor ignores the request if the button or
+    /// finger was released already.
+    pub fn start_move(&self, seat: &str, serial: u32) {
+        self.actions.borrow_mut().push(Action::Move {
+            seat: seat.to_owned(),
+            serial,
+        });
+    }
+
+    /// Lets the user resize the window by dragging `edge`.
+    ///
+    /// `seat` and `serial` are those of the button press or touch that
+    /// starts the resize, as for [`WindowHandle::start_move`].
+    pub fn start_resize(&self, seat: &str, serial: u32, edge: ResizeEdge) {
+        self.actions.borrow_mut().push(Action::Resize {
+            seat: seat.to_owned(),
+            serial,
+            edge,
+        });
+    }
+
+    fn request(&self, request: Request) {
+        self.requests.borrow_mut().push(request);
+    }
+
+    /// Returns the requests made since the last call.
+    fn take_requests(&self) -> Vec<Request> {
+        std::mem::replace(&mut *self.requests.borrow_mut(), Vec::new())
+    }
+
+    /// Returns the moves and resizes started since the last call.
+    fn take_actions(&self) -> Vec<Action> {
+        std::mem::replace(&mut *self.actions.borrow_mut(), Vec::new())
+    }
+
+    /// Returns true if a frame should be drawn now.
+    ///
+    /// Resets the dirty flag, so every request results in a single frame.
+    fn take_frame(&self) -> bool {
+        if self.frame_pending.get() || !(self.dirty.get() || self.animating.get()) {
+            return false;
+        }
+        self.dirty.set(false);
+        true
+    }
+}
+
+/// A toplevel window drawn with Pathfinder.
+///
+/// Owns the connection to the compositor, the EGL context, the renderer and
+/// the event loop. `A` is the application the window calls into.
+pub struct Window<A> {
+    display: Display,
+    event_queue: EventQueue,
+    // Waits for the compositor and all sources added by the application.
+    event_loop: EventLoop<A>,
+    wayland_source: Option<Source<Generic<Fd>>>,
+    // Polls the tasks spawned with `spawn_local`.
+    executor_source: Option<(u64, Source<PingSource>)>,
+    // Set by the event loop when the compositor sent something.
+    wayland_readable: Rc<Cell<bool>>,
+    shell_surface: ShellSurface,
+    decorations: Decorations,
+    surface: Main<wl_surface::WlSurface>,
+    // Both are dropped by hand, the renderer while the context is current.
+    context: ManuallyDrop<Context>,
+    renderer: ManuallyDrop<Renderer<GLDevice>>,
+    font_context: CanvasFontContext,
+    // window width and height, including the title bar but not the shadow
+    //
+    // This is the only place the window size is stored. It is updated once
+    // the compositor configures the window and read by the render code.
+    window_size: Rc<Cell<Vector2I>>,
+    // The state of the window the application was last told about.
+    window_state: WindowState,
+    // Needed to open the window again on a new connection.
+    title: String,
+    app_id: Option<String>,
+    client_decorations: bool,
+    // The logical size and scale the EGL surface and renderer were last set
+    // up for. The surface itself is `surface_size * scale` pixels large.
+    surface_size: Vector2I,
+    scale: f32,
+    // The size of the area the application draws to.
+    content_size: Vector2I,
+    handle: WindowHandle,
+    seats: Seats,
+    outputs: Outputs,
+    fractional_scale: Option<FractionalScale>,
+}
+
+impl<A: Application> Window<A> {
+    /// Connects to the compositor and opens a window with the given title.
+    ///
+    /// Fails if the compositor lacks required globals or OpenGL is not
+    /// available.
+    pub fn new(title: &str) -> Result<Window<A>, Error> {
+        let event_loop = EventLoop::new().map_err(Error::EventLoop)?;
+        Window::connect(title, WindowHandle::default(), Seats::default(), event_loop)
+    }
+
+    /// Opens a window, keeping the handle, seat settings and event loop of a
+    /// previous connection.
+    fn connect(
+        title: &str,
+        handle: WindowHandle,
+        seats: Seats,
+        event_loop: EventLoop<A>,
+    ) -> Result<Window<A>, Error> {
+        let display = Display::connect_to_env()?;
+        let mut event_queue = display.create_event_queue();
+        // The events are read by `dispatch`, the event loop only tells when
+        // there is something to read.
+        let wayland_readable = Rc::new(Cell::new(false));
+        let readable = wayland_readable.clone();
+        let wayland_source = event_loop
+            .handle()
+            .insert_source(
+                Generic::from_fd(display.get_connection_fd(), Interest::Readable, Mode::Level),
+                move |_, _, _| {
+                    readable.set(true);
+                    Ok(())
+                },
+            )
+            .map_err(|err| Error::EventLoop(err.into()))?;
+        let attached_display = (*display).clone().attach(event_queue.token());
+        // Seats are tracked as they come and go for the whole lifetime of
+        // the window.
+        let seats_handle = seats.clone();
+        let outputs = handle.outputs.clone();
+        let outputs_handle = outputs.clone();
+        let globals = GlobalManager::new_with_cb(&attached_display, move |event, registry, _| {
+            seats_handle.handle_global(&event, &registry);
+            outputs_handle.handle_global(&event, &registry);
+        });
+
+        // roundtrip to retrieve the globals list
+        event_queue
+            .sync_roundtrip(&mut (), |_, _, _| unreachable!())
+            .map_err(|err| Error::connection(&display, err))?;
+
+        let window_size = Rc::new(Cell::new(Vector2I::new(DEFAULT_SIZE.0, DEFAULT_SIZE.1)));
+
+        /*
+         * Init wayland objects
+         */
+
+        // The compositor allows us to creates surfaces
+        //
+        // Version 3 is needed to draw at a higher scale.
+        let compositor = globals
+            .instantiate_range::<wl_compositor::WlCompositor>(1, 3)
+            .map_err(|_| Error::missing_global(&globals, "wl_compositor", 1))?;
+        let surface = compositor.create_surface();
+        outputs.track_surface(&surface);
+        let fractional_scale = FractionalScale::new(&globals, &surface);
+        if let Ok(shm) = globals.instantiate_exact::<wl_shm::WlShm>(1) {
+            seats.init_cursors(compositor.clone(), &shm);
+        }
+        if let Ok(manager) = globals.instantiate_exact::<WpCursorShapeManagerV1>(1) {
+            seats.init_cursor_shapes(manager);
+        }
+        let shell_surface = ShellSurface::new(
+            &globals,
+            &surface,
+            title,
+            window_size.clone(),
+            handle.state.clone(),
+            handle.close_requested.clone(),
+        )?;
+        let mut decorations = Decorations::new(&globals, &shell_surface, title, handle.state.clone());
+
+        // An xdg_surface must not have a buffer attached before it was configured
+        // for the first time, so commit the bare surface and wait for the initial
+        // configure event before drawing anything.
+        if shell_surface.needs_configure() {
+            surface.commit();
+            event_queue
+                .sync_roundtrip(&mut (), |_, _, _| { /* we ignore unfiltered messages */ })
+                .map_err(|err| Error::connection(&display, err))?;
+        }
+
+        let surface_size = decorations.surface_size(window_size.get());
+        shell_surface.set_window_geometry(decorations.window_geometry(window_size.get()));
+        let content_size = decorations.content_rect(window_size.get()).size().to_i32();
+
+        // The renderer owns the GPU resources (shaders, buffers, textures), so it
+        // is created once and reused for every frame.
+        //
+        // The shadow around the window is drawn on a transparent background.
+        let (context, renderer) = Context::new(&display, &surface, surface_size, ColorF::transparent_black())?;
+        // Drivers without an alpha channel would show the shadow black. The
+        // first turn shrinks the surface to the size without it.
+        decorations.set_transparent(context.transparent());
+
+        event_queue
+            .sync_roundtrip(&mut (), |_, _, _| { /* we ignore unfiltered messages */ })
+            .map_err(|err| Error::connection(&display, err))?;
+
+        handle.request_redraw();
+
+        // Registered last, nothing would unregister it if a step above failed.
+        let executor_source = executor::register(&event_loop.handle())?;
+
+        Ok(Window {
+            display,
+            event_queue,
+            event_loop,
+            wayland_source: Some(wayland_source),
+            executor_source: Some(executor_source),
+            wayland_readable,
+            shell_surface,
+            decorations,
+            surface,
+            context: ManuallyDrop::new(context),
+            renderer: ManuallyDrop::new(renderer),
+            font_context: CanvasFontContext::from_system_source(),
+            window_size,
+            window_state: WindowState::default(),
+            title: title.to_owned(),
+            app_id: None,
+            client_decorations: true,
+            surface_size,
+            scale: 1.0,
+            content_size,
+            handle,
+            seats,
+            outputs,
+            fractional_scale,
+        })
+    }
+
+    /// Returns the OpenGL flavor that was negotiated with the driver.
+    pub fn gl_version(&self) -> GlVersion {
+        self.context.gl_version()
+    }
+
+    /// Also reports the first finger on a touchscreen as a pointer with the
+    /// left button pressed.
+    ///
+    /// Useful for applications that only handle pointer input. Touch events
+    /// are still delivered.
+    pub fn set_touch_emulates_pointer(&mut self, enabled: bool) {
+        self.seats.set_touch_emulates_pointer(enabled);
+    }
+
+    /// Lets bean draw a title bar and shadow if the compositor does not
+    /// decorate the window.
+    ///
+    /// Enabled by default. Applications that draw their own title bar turn
+    /// it off, the contents then cover the whole window. They let the user
+    /// move and resize the window with [`WindowHandle::start_move`] and
+    /// [`WindowHandle::start_resize`].
+    pub fn set_client_decorations(&mut self, enabled: bool) {
+        self.client_decorations = enabled;
+        self.decorations.set_enabled(enabled);
+        self.handle.request_redraw();
+    }
+
+    /// Returns a handle to schedule redraws of this window.
+    pub fn handle(&self) -> WindowHandle {
+        self.handle.clone()
+    }
+
+    /// Returns a handle to add event sources to the event loop of the window.
+    ///
+    /// Timers, Unix signals, channels and arbitrary file descriptors are
+    /// watched on the same thread that draws the window, their callbacks get
+    /// the application. Use a [`WindowHandle`] in the callback to redraw the
+    /// window. Sources stay registered if the window is opened again after a
+    /// disconnect.
+    pub fn loop_handle(&self) -> LoopHandle<A> {
+        self.event_loop.handle()
+    }
+
+    /// Creates a proxy to send events of type `T` from other threads.
+    ///
+    /// `callback` is called on the thread of the window for every event, in
+    /// the order they were sent. It can change the application and redraw
+    /// the window with the [`WindowHandle`].
+    pub fn create_proxy<T, F>(&self, mut callback: F) -> Result<EventLoopProxy<T>, Error>
+    where
+        T: 'static,
+        F: FnMut(&mut A, &WindowHandle, T) + 'static,
+    {
+        let (sender, channel): (_, Channel<T>) = channel::channel();
+        // The callback keeps the channel open as long as the event loop
+        // exists, a closed channel would wake the loop forever.
+        let keep_open = sender.clone();
+        let handle = self.handle.clone();
+        self.event_loop
+            .handle()
+            .insert_source(channel, move |event, _, app| {
+                let _ = &keep_open;
+                if let channel::Event::Msg(event) = event {
+                    callback(app, &handle, event);
+                }
+            })
+            .map_err(|err| Error::EventLoop(err.into()))?;
+        Ok(EventLoopProxy::new(sender))
+    }
+
+    /// Runs the event loop, calling into `app` for input and drawing.
+    ///
+    /// Returns once the window was closed, or with an error if the
+    /// connection to the compositor failed.
+    pub fn run(mut self, mut app: A) -> Result<(), Error> {
+        loop {
+            if self.handle.close_requested.replace(false) {
+                app.on_close_request(&self.handle);
+            }
+            if self.handle.closed.get() {
+                return Ok(());
+            }
+
+            if let Err(error) = self.turn(&mut app) {
+                // The application may save its state or try another compositor.
+                if !error.is_disconnect() || !app.on_disconnect(&self.handle, &error) {
+                    return Err(error);
+                }
+                self = self.reconnect()?;
+            }
+        }
+    }
+
+    /// Applies pending changes, draws a frame if needed and handles the
+    /// events that arrive until the next frame is due.
+    fn turn(&mut self, app: &mut A) -> Result<(), Error> {
+        for request in self.handle.take_requests() {
+            self.request(request);
+        }
+        for action in self.handle.take_actions() {
+            self.perform(action);
+        }
+
+        // Maximizing or activating the window changes the decorations.
+        let window_state = self.handle.state();
+        if window_state != self.window_state {
+            self.window_state = window_state;
+            self.handle.request_redraw();
+            app.on_state_change(&self.handle, window_state);
+        }
+
+        // The compositor changed the window size or the window moved to an
+        // output with a different scale.
+        let window_size = self.window_size.get();
+        let new_size = self.decorations.surface_size(window_size);
+        let new_scale = self.scale();
+        if new_size != self.surface_size || new_scale != self.scale {
+            self.surface_size = new_size;
+            self.scale = new_scale;
+            // Takes effect with the next commit, together with the new buffer.
+            if let Some(fractional_scale) = &self.fractional_scale {
+                fractional_scale.set_size(new_size);
+            } else if self.surface.as_ref().version() >= 3 {
+                self.surface.set_buffer_scale(new_scale as i32);
+            }
+            self.shell_surface
+                .set_window_geometry(self.decorations.window_geometry(window_size));
+            let physical_size = self.physical_size();
+            self.context.make_current()?;
+            self.context.resize(physical_size);
+            self.renderer
+                .replace_dest_framebuffer(DestFramebuffer::full_window(physical_size));
+            self.handle.request_redraw();
+        }
+        let content_size = self.decorations.content_rect(window_size).size().to_i32();
+        if content_size != self.content_size {
+            self.content_size = content_size;
+            app.on_resize(&self.handle, content_size);
+        }
+
+        // Tasks waiting for the next frame change the application before it
+        // is drawn.
+        if !self.handle.frame_pending.get() && executor::wake_frame_waiters() {
+            self.handle.request_redraw();
+            executor::run();
+        }
+        if self.handle.take_frame() {
+            self.draw_frame(app)?;
+        }
+
+        // Blocks until the compositor sends events, e.g. input or the frame
+        // callback, or another source of the event loop fires, so an idle
+        // window does not use any CPU time. Held keys and sleeping tasks are
+        // woken up on time.
+        let timeout = match (self.seats.next_repeat(), executor::next_timer()) {
+            (Some(repeat), Some(timer)) => Some(repeat.min(timer)),
+            (repeat, timer) => repeat.or(timer),
+        }
+        .map(|next| next.saturating_duration_since(Instant::now()));
+        self.dispatch(app, timeout)?;
+        executor::wake_timers();
+
+        let window_size = self.window_size.get();
+        for (seat, event) in self.seats.take_events(Instant::now()) {
+            if let Some(event) = self.decorations.handle_input(&self.handle, &seat, event, window_size) {
+                app.on_input(&self.handle, &seat, event);
+            }
+        }
+        for action in self.decorations.take_actions() {
+            self.perform(action);
+        }
+        let cursor = self.decorations.cursor().unwrap_or_else(|| self.handle.cursor.get());
+        // Cursors only support integer scales, they are scaled down instead.
+        self.seats.update_cursors(cursor, self.scale.ceil() as i32);
+        Ok(())
+    }
+
+    /// Opens the window again on a new connection to the compositor.
+    ///
+    /// The title, app id and decoration setting are restored, other requests
+    /// are lost.
+    fn reconnect(mut self) -> Result<Window<A>, Error> {
+        let title = self.title.clone();
+        let app_id = self.app_id.clone();
+        let client_decorations = self.client_decorations;
+        let handle = self.handle.clone();
+        let seats = self.seats.clone();
+        // The sources of the application move on to the new window.
+        self.remove_sources();
+        let event_loop = mem::replace(&mut self.event_loop, EventLoop::new().map_err(Error::EventLoop)?);
+        // Tears down what is left of the old connection.
+        drop(self);
+
+        // The old compositor will never send these.
+        handle.frame_pending.set(false);
+        handle.state.set(WindowState::default());
+        // Moves and resizes refer to serials of the old connection.
+        handle.take_actions();
+        if let Some(app_id) = app_id {
+            handle.set_app_id(&app_id);
+        }
+        handle.request_redraw();
+        let mut window = Window::connect(&title, handle, seats, event_loop)?;
+        window.set_client_decorations(client_decorations);
+        Ok(window)
+    }
+
+    /// Turns an error on the connection into the matching `Error`.
+    fn connection_error(&self, err: io::Error) -> Error {
+        Error::connection(&self.display, err)
+    }
+
+    /// Passes a request of the application on to the compositor.
+    fn request(&mut self, request: Request) {
+        match request {
+            Request::Title(title) => {
+                self.title = title.clone();
+                self.decorations.set_title(&title);
+                self.handle.request_redraw();
+                self.shell_surface.request(Request::Title(title));
+            }
+            // Limits are for the contents, the compositor wants them for the
+            // whole window.
+            Request::MinSize(size) if size == Vector2I::default() => {
+                self.shell_surface
+                    .request(Request::MinSize(Vector2I::new(MIN_SIZE.0, MIN_SIZE.1)));
+            }
+            Request::MinSize(size) => {
+                self.shell_surface
+                    .request(Request::MinSize(self.decorations.window_size(size)));
+            }
+            Request::MaxSize(size) if size != Vector2I::default() => {
+                self.shell_surface
+                    .request(Request::MaxSize(self.decorations.window_size(size)));
+            }
+            Request::AppId(app_id) => {
+                self.app_id = Some(app_id.clone());
+                self.shell_surface.request(Request::AppId(app_id));
+            }
+            request => self.shell_surface.request(request),
+        }
+    }
+
+    /// Carries out a click on the decorations or a move or resize started by
+    /// the application.
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Move { seat, serial } => {
+                if let Some(wl_seat) = self.seats.wl_seat(&seat) {
+                    self.shell_surface.start_move(&wl_seat, serial);
+                }
+            }
+            Action::Resize { seat, serial, edge } => {
+                if let Some(wl_seat) = self.seats.wl_seat(&seat) {
+                    self.shell_surface.start_resize(&wl_seat, serial, edge);
+                }
+            }
+            Action::Close => self.handle.close_requested.set(true),
+            Action::ToggleMaximized => self
+                .shell_surface
+                .request(Request::Maximized(!self.handle.state().maximized)),
+            Action::Minimize => self.shell_surface.request(Request::Minimize),
+        }
+    }
+
+    /// Returns the scale the window should be drawn at.
+    fn scale(&self) -> f32 {
+        if let Some(scale) = self.fractional_scale.as_ref().and_then(FractionalScale::preferred) {
+            return scale;
+        }
+        // Without set_buffer_scale or a viewport the compositor always
+        // assumes a scale of 1.
+        if self.fractional_scale.is_some() || self.surface.as_ref().version() >= 3 {
+            self.outputs.scale() as f32
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the size of the EGL surface in pixels.
+    fn physical_size(&self) -> Vector2I {
+        // Rounded halfway away from zero, like the compositor does.
+        let scale = |length: i32| (length as f32 * self.scale).round() as i32;
+        Vector2I::new(scale(self.surface_size.x()), scale(self.surface_size.y()))
+    }
+
+    /// Reads and dispatches events from the compositor and the other sources
+    /// of the event loop.
+    ///
+    /// Waits at most `timeout` for new events, or forever if it is `None`.
+    fn dispatch(&mut self, app: &mut A, timeout: Option<Duration>) -> Result<(), Error> {
+        let dispatched = self
+            .event_queue
+            .dispatch_pending(&mut (), |_, _, _| { /* we ignore unfiltered messages */ })
+            .map_err(|err| self.connection_error(err))?;
+        if dispatched > 0 {
+            return Ok(());
+        }
+
+        self.display.flush().map_err(|err| self.connection_error(err))?;
+        // Events that were queued in the meantime are dispatched right away.
+        let guard = self.event_queue.prepare_read();
+        let timeout = if guard.is_some() { timeout } else { Some(Duration::from_secs(0)) };
+        self.wayland_readable.set(false);
+        match self.event_loop.dispatch(timeout, app) {
+            Ok(()) => {}
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(Error::EventLoop(err)),
+        }
+        if let Some(guard) = guard {
+            if self.wayland_readable.get() {
+                guard.read_events().map_err(|err| self.connection_error(err))?;
+            } else {
+                guard.cancel();
+            }
+        }
+        self.event_queue
+            .dispatch_pending(&mut (), |_, _, _| { /* we ignore unfiltered messages */ })
+            .map_err(|err| self.connection_error(err))?;
+        Ok(())
+    }
+
+    fn draw_frame(&mut self, app: &mut A) -> Result<(), Error> {
+        self.context.make_current()?;
+
+        // Ask the compositor to tell us when it is a good time to draw the
+        // next frame. The request is part of the commit done by the swap.
+        self.handle.frame_pending.set(true);
+        let frame_pending = self.handle.frame_pending.clone();
+        self.surface.frame().quick_assign(move |_, event, _| {
+            if let wl_callback::Event::Done { .. } = event {
+                frame_pending.set(false);
+            }
+        });
+
+        // The canvas covers every pixel of the buffer, the decorations and the
+        // application draw in logical units.
+        let mut canvas =
+            CanvasRenderingContext2D::new(self.font_context.clone(), self.physical_size().to_f32());
+        let scale = Transform2F::from_scale(Vector2F::splat(self.scale));
+        canvas.set_transform(&scale);
+        let window_size = self.window_size.get();
+        self.decorations.draw(&mut canvas, window_size);
+
+        // The application draws relative to its content area and cannot draw
+        // over the decorations.
+        let content = self.decorations.content_rect(window_size);
+        canvas.save();
+        canvas.set_transform(&(scale * Transform2F::from_translation(content.origin())));
+        let mut clip = Path2D::new();
+        clip.rect(RectF::new(Vector2F::default(), content.size()));
+        canvas.clip_path(clip, FillRule::Winding);
+        app.draw(&mut canvas);
+        canvas.restore();
+        render::render_canvas(canvas, &mut self.renderer);
+
+        if let Err(err) = self.context.swap_buffers() {
+            // EGL fails as well once the compositor is gone, the connection
+            // tells the actual reason.
+            if let Err(io_err) = self.display.flush() {
+                return Err(self.connection_error(io_err));
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+impl<A> Window<A> {
+    /// Stops watching the connection to the compositor and polling tasks.
+    ///
+    /// The sources added by the application stay in the event loop.
+    fn remove_sources(&mut self) {
+        if let Some(source) = self.wayland_source.take() {
+            self.event_loop.handle().remove(source);
+        }
+        if let Some((id, source)) = self.executor_source.take() {
+            executor::unregister(id);
+            self.event_loop.handle().remove(source);
+        }
+    }
+}
+
+impl<A> Drop for Window<A> {
+    fn drop(&mut self) {
+        // Pathfinder frees its GPU resources when dropped, which only works
+        // while the context is current. Another window may have made its own
+        // context current since.
+        let _ = self.context.make_current();
+        unsafe {
+            ManuallyDrop::drop(&mut self.renderer);
+            ManuallyDrop::drop(&mut self.context);
+        }
+        // Objects extending the surface go before the surface itself.
+        if let Some(fractional_scale) = &self.fractional_scale {
+            fractional_scale.destroy();
+        }
+        self.decorations.destroy();
+        self.shell_surface.destroy();
+        self.surface.destroy();
+        self.seats.release();
+        self.outputs.clear();
+        self.remove_sources();
+        // Nobody is left to report the error to.
+        let _ = self.display.flush();
+    }
+}
@@ -0,0 +1,20 @@
+use bean::testing::{assert_golden, Tolerance};
+use pathfinder_geometry::vector::Vector2I;
+
+#[path = "../examples/house/drawing.rs"]
+mod drawing;
+
+#[test]
+fn house() {
+    // Drivers antialias the slanted roof slightly differently.
+    let tolerance = Tolerance {
+        channel: 8,
+        max_differing_pixels: 64,
+    };
+    assert_golden(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/house.png"),
+        Vector2I::new(320, 240),
+        tolerance,
+        drawing::draw_house,
+    );
+}